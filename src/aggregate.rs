@@ -0,0 +1,255 @@
+//! Aggregate functions of the return clause
+//!
+//! If the return clause contains aggregate functions, all matching documents are combined into a
+//! single result. With `group()` there's one result per distinct combination of the grouped
+//! values instead, ordered by those values. Values that don't exist (and have no default) are
+//! ignored by all functions.
+
+use std::cmp::Ordering;
+
+use serde_json::{self, Value as Json};
+
+use ast::{Aggregate, OrderType, Path, Projection, ProjectionKind};
+use eval::{collate, lookup, lookup_existing, to_json, EvalError};
+
+/// Returns whether the projection contains any aggregate functions
+pub fn has_aggregates(projection: &Projection) -> bool {
+    match projection.kind {
+        ProjectionKind::Aggregate(_) => true,
+        ProjectionKind::Object(ref members) => {
+            members.iter().any(|(_, value)| has_aggregates(value))
+        },
+        ProjectionKind::Array(ref values) => values.iter().any(has_aggregates),
+        _ => false,
+    }
+}
+
+/// Applies the aggregate functions of the projection to the documents
+pub fn aggregate(projection: &Projection, documents: &[&Json]) -> Result<Vec<Json>, EvalError> {
+    let mut groups_by = Vec::new();
+    collect_groups(projection, &mut groups_by);
+
+    // The documents are grouped in the order they come in, so that e.g. `array()` keeps the order
+    // of the order clause
+    let mut groups: Vec<(Vec<Json>, Vec<&Json>)> = Vec::new();
+    for &document in documents {
+        let key: Vec<Json> = groups_by.iter().map(|&(path, _)| lookup(document, path)).collect();
+        match groups.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, members)) => members.push(document),
+            None => groups.push((key, vec![document])),
+        }
+    }
+    if groups_by.is_empty() && groups.is_empty() {
+        groups.push((Vec::new(), Vec::new()));
+    }
+    groups.sort_by(|aa, bb| {
+        for (ii, &(_, order)) in groups_by.iter().enumerate() {
+            let ordering = match order {
+                OrderType::Desc => collate(&bb.0[ii], &aa.0[ii]),
+                OrderType::Asc | OrderType::None => collate(&aa.0[ii], &bb.0[ii]),
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    });
+
+    groups.iter().map(|(key, members)| {
+        let mut group_values = key.iter();
+        project(projection, members, &mut group_values)
+    }).collect()
+}
+
+/// Collects the paths of all `group()` calls in the order they appear
+fn collect_groups<'a>(projection: &'a Projection, groups: &mut Vec<(&'a Path, OrderType)>) {
+    match projection.kind {
+        ProjectionKind::Aggregate(Aggregate::Group(ref path, order)) => groups.push((path, order)),
+        ProjectionKind::Object(ref members) => {
+            for (_, value) in members {
+                collect_groups(value, groups);
+            }
+        },
+        ProjectionKind::Array(ref values) => {
+            for value in values {
+                collect_groups(value, groups);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Builds the result of a single group. `group_values` are the values of the `group()` calls,
+/// they are used in the same order as `collect_groups()` found them.
+fn project<'a, I>(projection: &Projection, documents: &[&Json], group_values: &mut I)
+                  -> Result<Json, EvalError>
+    where I: Iterator<Item = &'a Json>
+{
+    Ok(match projection.kind {
+        ProjectionKind::Aggregate(Aggregate::Group(..)) => {
+            group_values.next().cloned().unwrap_or(Json::Null)
+        },
+        ProjectionKind::Aggregate(ref function) => apply(function, documents),
+        ProjectionKind::Value(ref value) => to_json(value),
+        ProjectionKind::Object(ref members) => {
+            let mut object = serde_json::Map::new();
+            for (key, value) in members {
+                object.insert(key.name.clone(), project(value, documents, group_values)?);
+            }
+            Json::Object(object)
+        },
+        ProjectionKind::Array(ref values) => {
+            let mut array = Vec::with_capacity(values.len());
+            for value in values {
+                array.push(project(value, documents, group_values)?);
+            }
+            Json::Array(array)
+        },
+        ProjectionKind::All | ProjectionKind::Path(_) | ProjectionKind::Bind(..) |
+        ProjectionKind::Score => {
+            return Err(EvalError::Unsupported("returning values other than aggregates together \
+                                               with aggregate functions"))
+        },
+    })
+}
+
+/// Returns the values the path points to of all documents that have them
+fn values(path: &Path, documents: &[&Json]) -> Vec<Json> {
+    documents.iter().filter_map(|document| lookup_existing(document, path)).collect()
+}
+
+fn numbers(path: &Path, documents: &[&Json]) -> Vec<f64> {
+    values(path, documents).iter().filter_map(Json::as_f64).collect()
+}
+
+fn number(number: f64) -> Json {
+    serde_json::Number::from_f64(number).map_or(Json::Null, Json::Number)
+}
+
+fn flatten(value: Json, flat: &mut Vec<Json>) {
+    match value {
+        Json::Array(elements) => {
+            for element in elements {
+                flatten(element, flat);
+            }
+        },
+        value => flat.push(value),
+    }
+}
+
+/// The values, where arrays are replaced by their elements
+fn array_elements(path: &Path, documents: &[&Json]) -> Vec<Json> {
+    let mut elements = Vec::new();
+    for value in values(path, documents) {
+        match value {
+            Json::Array(values) => elements.extend(values),
+            value => elements.push(value),
+        }
+    }
+    elements
+}
+
+fn apply(function: &Aggregate, documents: &[&Json]) -> Json {
+    match *function {
+        Aggregate::Array(ref path) => Json::Array(values(path, documents)),
+        Aggregate::ArrayFlat(ref path) => {
+            let mut flat = Vec::new();
+            for value in values(path, documents) {
+                flatten(value, &mut flat);
+            }
+            Json::Array(flat)
+        },
+        Aggregate::Avg(ref path) => {
+            let numbers = numbers(path, documents);
+            if numbers.is_empty() {
+                Json::Null
+            } else {
+                number(numbers.iter().sum::<f64>() / numbers.len() as f64)
+            }
+        },
+        Aggregate::Count => Json::from(documents.len()),
+        Aggregate::Concat(ref path, ref separator) => {
+            let strings: Vec<String> = values(path, documents)
+                .into_iter()
+                .filter_map(|value| match value {
+                    Json::String(string) => Some(string),
+                    _ => None,
+                })
+                .collect();
+            Json::String(strings.join(separator.as_ref().map_or(",", |separator| &separator[..])))
+        },
+        // Groups are filled in by `project()`
+        Aggregate::Group(..) => Json::Null,
+        Aggregate::Max(ref path) => extreme(values(path, documents), Ordering::Greater),
+        Aggregate::MaxArray(ref path) => {
+            extreme(array_elements(path, documents), Ordering::Greater)
+        },
+        Aggregate::Min(ref path) => extreme(values(path, documents), Ordering::Less),
+        Aggregate::MinArray(ref path) => extreme(array_elements(path, documents), Ordering::Less),
+        Aggregate::Sum(ref path) => number(numbers(path, documents).iter().sum()),
+    }
+}
+
+/// Returns the largest (`Ordering::Greater`) or smallest (`Ordering::Less`) value
+fn extreme(values: Vec<Json>, wanted: Ordering) -> Json {
+    values.into_iter()
+        .fold(None, |best: Option<Json>, value| match best {
+            Some(best) if collate(&value, &best) != wanted => Some(best),
+            _ => Some(value),
+        })
+        .unwrap_or(Json::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use eval::evaluate;
+    use parse_query;
+    use serde_json::Value as Json;
+
+    fn run(query: &str) -> Json {
+        let documents = json!([
+            {"city": "Berlin", "age": 30, "name": "Alice", "scores": [1, [2, 3]]},
+            {"city": "Paris", "age": 25, "name": "Bob", "scores": [4]},
+            {"city": "Berlin", "age": 35, "name": "Carol"},
+            {"city": "Rome", "name": "Dave", "scores": 5}
+        ]);
+        let documents = documents.as_array().unwrap();
+        Json::Array(evaluate(&parse_query(query).unwrap(), documents).unwrap())
+    }
+
+    #[test]
+    fn without_groups() {
+        assert_eq!(run("find {} return count()"), json!([4]));
+        assert_eq!(run("find {} return [sum(.age), avg(.age), min(.age), max(.age)]"),
+                   json!([[90.0, 30.0, 25, 35]]));
+        assert_eq!(run(r#"find {} return {names: concat(.name sep=" & "), cities: concat(.city)}"#),
+                   json!([{"names": "Alice & Bob & Carol & Dave",
+                           "cities": "Berlin,Paris,Berlin,Rome"}]));
+        assert_eq!(run(r#"find {city: == "Nowhere"} return [count(), sum(.age), avg(.age)]"#),
+                   json!([[0, 0.0, null]]));
+    }
+
+    #[test]
+    fn arrays() {
+        assert_eq!(run("find {} return array(.age)"), json!([[30, 25, 35]]));
+        assert_eq!(run("find {} return array(.age default=0)"), json!([[30, 25, 35, 0]]));
+        assert_eq!(run("find {} return array_flat(.scores)"), json!([[1, 2, 3, 4, 5]]));
+        assert_eq!(run("find {} return [min_array(.scores), max_array(.scores)]"),
+                   json!([[1, [2, 3]]]));
+        assert_eq!(run("find {} order .age desc return array(.name)"),
+                   json!([["Carol", "Alice", "Bob", "Dave"]]));
+    }
+
+    #[test]
+    fn groups() {
+        assert_eq!(run("find {} return {city: group(.city), count: count()}"),
+                   json!([{"city": "Berlin", "count": 2},
+                          {"city": "Paris", "count": 1},
+                          {"city": "Rome", "count": 1}]));
+        assert_eq!(run("find {} return [group(.city desc), array(.name)] limit 2"),
+                   json!([["Rome", ["Dave"]], ["Paris", ["Bob"]]]));
+        assert_eq!(run("find {} return [group(.city), group(.age desc), count()]"),
+                   json!([["Berlin", 35, 1], ["Berlin", 30, 1], ["Paris", 25, 1],
+                          ["Rome", null, 1]]));
+    }
+}
@@ -1,3 +1,83 @@
+//! The typed syntax tree of a query
+//!
+//! Every node knows the byte range of the query text it was parsed from. Spans are ignored when
+//! nodes are compared, two nodes are equal if they mean the same, no matter how they were
+//! written. For the same reason `Debug` only prints what a node means.
+//!
+//! With the `serde` feature the tree can be serialized and deserialized, e.g. to and from JSON.
+//! Enum variants are written in snake case, nodes as an object with their `kind` and `span`.
+//! Spans and comments may be left out when deserializing. See `schema` for the JSON format.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A byte range within the query text
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+/// Defines a node that consists of a kind and the span it was parsed from
+macro_rules! node {
+    ($(#[$attr:meta])* $name:ident, $kind:ident) => {
+        $(#[$attr])*
+        #[derive(Clone)]
+        #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+        pub struct $name {
+            pub kind: $kind,
+            #[cfg_attr(feature = "serde", serde(default))]
+            pub span: Span,
+        }
+
+        impl $name {
+            pub fn new(kind: $kind, span: Span) -> $name {
+                $name { kind, span }
+            }
+
+            pub fn kind(&self) -> &$kind {
+                &self.kind
+            }
+
+            /// The byte range of the node within the query
+            pub fn span(&self) -> Span {
+                self.span
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &$name) -> bool {
+                self.kind == other.kind
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Debug::fmt(&self.kind, f)
+            }
+        }
+
+        /// Nodes that weren't parsed from a query have an empty span
+        impl From<$kind> for $name {
+            fn from(kind: $kind) -> $name {
+                $name::new(kind, Span::default())
+            }
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
 #[derive(Debug)]
 pub enum OperatorType {
     Equal,
@@ -11,73 +91,408 @@ pub enum OperatorType {
     WordMatch(Option<u64>),
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
 #[derive(Debug)]
 pub enum BoolType {
     And,
     Or,
 }
 
-#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum OrderType {
     None,
     Asc,
     Desc,
 }
 
-#[derive(Debug, PartialEq)]
-pub enum Token {
-    Array(Box<Token>),
-    Bbox(f64, f64, f64, f64),
-    Bind(String, Box<Token>),
-    JsonArray(Vec<Token>),
-    JsonBool(bool),
-    JsonNull,
-    JsonNumber(f64),
-    JsonObject(String, Box<Token>),
-    JsonObjectEmpty,
-    JsonString(String),
-    Literal(String),
-    Not(Box<Token>),
-    Object(String, Box<Token>),
-    Parameter(String),
-    Boost(u64, Box<Token>),
+/// A name within a query, e.g. a key of an object or a variable
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
+pub struct Ident {
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
+impl Ident {
+    pub fn new(name: String, span: Span) -> Ident {
+        Ident { name, span }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl PartialEq for Ident {
+    fn eq(&self, other: &Ident) -> bool {
+        self.name == other.name
+    }
+}
+
+impl fmt::Debug for Ident {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.name, f)
+    }
+}
+
+impl fmt::Display for Ident {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl<'a> From<&'a str> for Ident {
+    fn from(name: &'a str) -> Ident {
+        Ident::new(name.to_string(), Span::default())
+    }
+}
+
+/// A parsed query
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
+pub struct Query {
+    pub find: Predicate,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub order: Vec<OrderKey>,
+    pub ret: Option<Projection>,
+    pub limit: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+    /// The comments of the query in the order they appear. They are only kept if the query was
+    /// parsed with `parse_query_with_comments()`. A comment belongs to the node that follows it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub comments: Vec<Comment>,
+}
+
+impl Query {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl PartialEq for Query {
+    fn eq(&self, other: &Query) -> bool {
+        self.find == other.find && self.order == other.order && self.ret == other.ret &&
+            self.limit == other.limit
+    }
+}
+
+impl fmt::Debug for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Query")
+            .field("find", &self.find)
+            .field("order", &self.order)
+            .field("ret", &self.ret)
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+/// A `# ...` or `/* ... */` comment
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comment {
+    /// The comment as it was written, including `#` or `/*` and `*/`
+    pub text: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
+node!(
+    /// A condition of the find clause
+    Predicate, PredicateKind);
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+#[derive(Clone, Debug, PartialEq)]
+pub enum PredicateKind {
+    /// `find {}` matches all documents
+    All,
+    /// The value of the key is an object the predicate is applied to
+    Object(Ident, Box<Predicate>),
+    /// At least one element of the array needs to match the predicate
+    Array(Box<Predicate>),
+    /// Binds the matching array elements to a variable
+    Bind(Ident, Box<Predicate>),
+    Not(Box<Predicate>),
+    Boost(u64, Box<Predicate>),
 
     // If the key is `None` then it's an operator within an array
-    Equal(Option<String>, Box<Token>),
-    Greater(Option<String>, Box<Token>),
-    GreaterEqual(Option<String>, Box<Token>),
-    Intersect(Option<String>, Box<Token>),
-    Less(Option<String>, Box<Token>),
-    LessEqual(Option<String>, Box<Token>),
-    WordMatch(Option<String>, Option<u64>, Box<Token>),
+    Equal(Option<Ident>, Value),
+    Greater(Option<Ident>, Value),
+    GreaterEqual(Option<Ident>, Value),
+    Intersect(Option<Ident>, Bbox),
+    Less(Option<Ident>, Value),
+    LessEqual(Option<Ident>, Value),
+    WordMatch(Option<Ident>, Option<u64>, Value),
+
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+}
+
+node!(
+    /// The right hand side of a comparison within the find clause
+    Value, ValueKind);
 
-    And(Box<Token>, Box<Token>),
-    Or(Box<Token>, Box<Token>),
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValueKind {
+    Null,
+    Bool(bool),
+    Number(JsonNumber),
+    String(String),
+    Parameter(String),
+}
+
+/// A bounding box given as `[W, S, E, N]`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
+pub struct Bbox {
+    pub west: f64,
+    pub south: f64,
+    pub east: f64,
+    pub north: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
+impl Bbox {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
 
-    Order(Box<Option<Token>>, OrderType),
+impl PartialEq for Bbox {
+    fn eq(&self, other: &Bbox) -> bool {
+        self.west == other.west && self.south == other.south && self.east == other.east &&
+            self.north == other.north
+    }
+}
+
+impl fmt::Debug for Bbox {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Bbox")
+            .field("west", &self.west)
+            .field("south", &self.south)
+            .field("east", &self.east)
+            .field("north", &self.north)
+            .finish()
+    }
+}
 
+/// JSON as it is used for literals and default values in the return clause
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(JsonNumber),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+/// A number as it is written within a query, without losing any precision
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+#[derive(Clone, PartialEq)]
+pub enum JsonNumber {
+    /// A negative integer
+    Int(i64),
+    /// A non-negative integer
+    #[cfg_attr(feature = "serde", serde(rename = "uint"))]
+    UInt(u64),
+    /// A number with a fraction or an exponent that fits into an `f64`
+    Float(f64),
+    /// A number with more digits than any of the other types can hold, as it was written
+    Big(String),
+}
+
+impl JsonNumber {
+    /// Parses a number written as JSON. It's `None` if it's beyond the range of an `f64`, either
+    /// too large or so close to zero that it would become zero.
+    pub fn parse(text: &str) -> Option<JsonNumber> {
+        if !text.bytes().any(|byte| matches!(byte, b'.' | b'e' | b'E')) {
+            if let Ok(uint) = u64::from_str(text) {
+                return Some(JsonNumber::UInt(uint));
+            }
+            if let Ok(int) = i64::from_str(text) {
+                // `-0` is zero and not negative
+                return Some(if int < 0 { JsonNumber::Int(int) } else { JsonNumber::UInt(0) });
+            }
+        }
+        let float = f64::from_str(text).ok().filter(|float| float.is_finite())?;
+        let mantissa = text.split(['e', 'E']).next().unwrap_or("");
+        let digits = mantissa.trim_start_matches(['-', '0', '.'])
+            .bytes()
+            .filter(u8::is_ascii_digit)
+            .count();
+        // Only a mantissa of zeros is zero, anything else is too close to zero for an `f64`
+        if float == 0.0 && digits > 0 {
+            return None;
+        }
+        // An `f64` holds any number with up to 15 significant digits
+        if digits <= f64::DIGITS as usize {
+            Some(JsonNumber::Float(float))
+        } else {
+            Some(JsonNumber::Big(text.to_string()))
+        }
+    }
+
+    /// The closest `f64`, which might be less precise than the number
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            JsonNumber::Int(int) => int as f64,
+            JsonNumber::UInt(uint) => uint as f64,
+            JsonNumber::Float(float) => float,
+            JsonNumber::Big(ref text) => f64::from_str(text).unwrap_or(f64::NAN),
+        }
+    }
+
+    /// Compares the values of two numbers. Integers are compared exactly.
+    pub fn compare(&self, other: &JsonNumber) -> Option<Ordering> {
+        match (self.as_i128(), other.as_i128()) {
+            (Some(aa), Some(bb)) => Some(aa.cmp(&bb)),
+            _ => self.as_f64().partial_cmp(&other.as_f64()),
+        }
+    }
+
+    fn as_i128(&self) -> Option<i128> {
+        match *self {
+            JsonNumber::Int(int) => Some(i128::from(int)),
+            JsonNumber::UInt(uint) => Some(i128::from(uint)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Debug for JsonNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// A condition of the order clause
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
+pub struct OrderKey {
+    /// It's `None` if only the direction was given, e.g. `order asc`
+    pub by: Option<OrderBy>,
+    pub order: OrderType,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
+impl OrderKey {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl PartialEq for OrderKey {
+    fn eq(&self, other: &OrderKey) -> bool {
+        self.by == other.by && self.order == other.order
+    }
+}
+
+impl fmt::Debug for OrderKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OrderKey")
+            .field("by", &self.by)
+            .field("order", &self.order)
+            .finish()
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+#[derive(Clone, Debug, PartialEq)]
+pub enum OrderBy {
+    Path(Path),
+    Score,
+}
+
+node!(
+    /// The return clause or a part of it
+    Projection, ProjectionKind);
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProjectionKind {
+    /// `.` returns the whole document
     All,
-    Path(Vec<Token>),
-    PathArray(Option<u64>),
-    ReturnArray(Vec<Token>),
-    ReturnBind(String, Box<Option<Token>>),
-    ReturnObject(Vec<Token>),
-    Default(Box<Token>, Box<Token>),
-
-    GroupArray(Box<Token>),
-    ArrayFlat(Box<Token>),
-    Avg(Box<Token>),
-    Count,
-    Concat(Box<Token>, Option<String>),
-    Group(Box<Token>, OrderType),
-    Max(Box<Token>),
-    MaxArray(Box<Token>),
-    Min(Box<Token>),
-    MinArray(Box<Token>),
+    Value(JsonValue),
+    Path(Path),
+    /// A variable that was bound in the find clause, optionally followed by a path
+    Bind(Ident, Option<Path>),
+    Object(Vec<(Ident, Projection)>),
+    Array(Vec<Projection>),
+    /// The span of the function is the one of the projection
+    Aggregate(Aggregate),
     Score,
-    Sum(Box<Token>),
+}
+
+/// The aggregate functions of the return clause
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Aggregate {
+    Array(Path),
+    ArrayFlat(Path),
+    Avg(Path),
+    Count,
+    Concat(Path, Option<String>),
+    Group(Path, OrderType),
+    Max(Path),
+    MaxArray(Path),
+    Min(Path),
+    MinArray(Path),
+    Sum(Path),
+}
+
+/// A path into a document like `.some.field[0]`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
+pub struct Path {
+    pub parts: Vec<PathPart>,
+    /// The value that is returned if the path doesn't exist
+    pub default: Option<JsonValue>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
+impl Path {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl PartialEq for Path {
+    fn eq(&self, other: &Path) -> bool {
+        self.parts == other.parts && self.default == other.default
+    }
+}
+
+impl fmt::Debug for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Path")
+            .field("parts", &self.parts)
+            .field("default", &self.default)
+            .finish()
+    }
+}
 
-    Limit(u64),
+node!(
+    /// A single step of a path
+    PathPart, PathPartKind);
 
-    Noise(Box<Token>, Vec<Token>, Box<Option<Token>>, Box<Option<Token>>),
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathPartKind {
+    Field(String),
+    Index(u64),
+    /// `[]` selects all elements of an array
+    Array,
 }
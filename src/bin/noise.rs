@@ -0,0 +1,270 @@
+extern crate noise_lalrpop;
+#[macro_use]
+extern crate serde_json;
+
+use std::env;
+use std::fs;
+use std::process;
+
+use serde_json::Value as JsonValue;
+
+use noise_lalrpop::ast::{Query, Span};
+use noise_lalrpop::format::{format_query, FormatOptions};
+#[cfg(feature = "serde")]
+use noise_lalrpop::schema;
+use noise_lalrpop::split::{split_queries, QueryText};
+use noise_lalrpop::validate::{Diagnostic, Severity};
+use noise_lalrpop::{parse_query, parse_query_with_comments, validate, NoiseError};
+
+const USAGE: &str = "\
+Usage: noise [--format <text|json>] <file>...
+       noise fmt [--indent <n>] [--width <n>] [--write] <file>...
+
+Without a command every query of the files is parsed and validated. Queries are separated by `;`,
+blank lines or lines that start with `find`. With `--format json` the result is a single JSON
+object that contains the syntax tree or the errors of every query. `fmt` prints the files with all
+queries formatted, `--write` writes them back to the files instead.";
+
+pub fn main() {
+    let mut args = env::args().skip(1).peekable();
+    match args.peek().map(|arg| &arg[..]) {
+        Some("fmt") => {
+            args.next();
+            fmt(args.collect());
+        },
+        Some("-h") | Some("--help") | None => println!("{}", USAGE),
+        Some(_) => check(args.collect()),
+    }
+}
+
+/// The results of all queries that were checked
+#[derive(Default)]
+struct Summary {
+    passed: usize,
+    failed: usize,
+    /// The result of every query if the output is JSON
+    queries: Vec<JsonValue>,
+}
+
+fn check(args: Vec<String>) {
+    let mut json = false;
+    let mut filenames = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--format" => json = match args.next().as_ref().map(|format| &format[..]) {
+                Some("text") => false,
+                Some("json") => true,
+                _ => {
+                    eprintln!("error: --format needs `text` or `json` as value\n\n{}", USAGE);
+                    process::exit(2);
+                },
+            },
+            _ => filenames.push(arg),
+        }
+    }
+
+    let mut summary = Summary::default();
+    for filename in filenames {
+        parse_file(&filename, json, &mut summary);
+    }
+    if json {
+        let mut output = json!({
+            "passed": summary.passed,
+            "failed": summary.failed,
+            "queries": summary.queries,
+        });
+        add_version(&mut output);
+        println!("{}", serde_json::to_string_pretty(&output).expect("JSON can be serialized"));
+    } else {
+        println!("{} passed, {} failed", summary.passed, summary.failed);
+    }
+    if summary.failed > 0 {
+        process::exit(1);
+    }
+}
+
+/// Parses and validates every query of a file. Errors are reported with the line the query starts
+/// at and positions within the file, the remaining queries are still checked. A file that can't be
+/// read counts as a single failed query.
+fn parse_file(filename: &str, json: bool, summary: &mut Summary) {
+    let contents = match fs::read_to_string(filename) {
+        Ok(contents) => contents,
+        Err(err) => {
+            let message = format!("cannot read the file: {}", err);
+            summary.failed += 1;
+            if json {
+                summary.queries.push(json!({
+                    "file": filename,
+                    "line": null,
+                    "query": null,
+                    "ok": false,
+                    "error": {"message": message},
+                    "diagnostics": [],
+                }));
+            } else {
+                println!("{}: error: {}", filename, message);
+            }
+            return;
+        },
+    };
+    for query in split_queries(&contents) {
+        let (ast, errors, diagnostics) = match parse_query(query.text) {
+            Ok(parsed) => {
+                let diagnostics = validate(&parsed);
+                (Some(parsed), None, diagnostics)
+            },
+            Err(err) => (None, Some(within_file(err, &query)), Vec::new()),
+        };
+        let failed = errors.is_some() || diagnostics.iter().any(Diagnostic::is_error);
+        if failed {
+            summary.failed += 1;
+        } else {
+            summary.passed += 1;
+        }
+
+        if json {
+            let mut entry = json!({
+                "file": filename,
+                "line": query.line,
+                "query": query.text,
+                "ok": !failed,
+                "error": errors.as_ref().map(error_to_json),
+                "diagnostics": diagnostics.iter().map(diagnostic_to_json).collect::<Vec<_>>(),
+            });
+            add_ast(&mut entry, &ast);
+            summary.queries.push(entry);
+            continue;
+        }
+        if let Some(err) = errors {
+            println!("{}:{}: {}", filename, query.line, err);
+        }
+        for diagnostic in &diagnostics {
+            println!("{}:{}: {}", filename, query.line, diagnostic);
+        }
+        if !failed {
+            println!("{}:{}: ok", filename, query.line);
+        }
+    }
+}
+
+/// The syntax tree and the version of its format are only written with the `serde` feature
+#[cfg(feature = "serde")]
+fn add_version(output: &mut JsonValue) {
+    output["version"] = json!(schema::VERSION);
+}
+
+#[cfg(not(feature = "serde"))]
+fn add_version(_output: &mut JsonValue) {}
+
+#[cfg(feature = "serde")]
+fn add_ast(entry: &mut JsonValue, ast: &Option<Query>) {
+    entry["ast"] = json!(ast);
+}
+
+#[cfg(not(feature = "serde"))]
+fn add_ast(_entry: &mut JsonValue, _ast: &Option<Query>) {}
+
+/// The span is relative to the query, the line and column are within the file
+fn error_to_json(err: &NoiseError) -> JsonValue {
+    json!({
+        "code": err.code.as_str(),
+        "message": err.message,
+        "span": span_to_json(err.span),
+        "line": err.line,
+        "column": err.column,
+        "token": err.token,
+        "expected": err.expected,
+    })
+}
+
+fn diagnostic_to_json(diagnostic: &Diagnostic) -> JsonValue {
+    json!({
+        "code": diagnostic.code.as_str(),
+        "severity": match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        },
+        "message": diagnostic.message,
+        "span": diagnostic.span.map(span_to_json),
+    })
+}
+
+fn span_to_json(span: Span) -> JsonValue {
+    json!({"start": span.start, "end": span.end})
+}
+
+/// Makes the line and column of an error relative to the file instead of the query
+fn within_file(mut err: NoiseError, query: &QueryText) -> NoiseError {
+    if err.line == 1 {
+        err.column += query.column - 1;
+    }
+    err.line += query.line - 1;
+    err
+}
+
+fn parse_number(option: &str, value: Option<String>) -> usize {
+    match value.as_ref().and_then(|value| value.parse().ok()) {
+        Some(number) => number,
+        None => {
+            eprintln!("error: {} needs a number as value\n\n{}", option, USAGE);
+            process::exit(2);
+        },
+    }
+}
+
+fn fmt(args: Vec<String>) {
+    let mut options = FormatOptions::default();
+    let mut write = false;
+    let mut filenames = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--indent" => options.indent = parse_number(&arg, args.next()),
+            "--width" => options.width = parse_number(&arg, args.next()),
+            "--write" => write = true,
+            _ => filenames.push(arg),
+        }
+    }
+
+    let mut failed = false;
+    for filename in filenames {
+        let contents = match fs::read_to_string(&filename) {
+            Ok(contents) => contents,
+            Err(err) => {
+                eprintln!("{}: error: cannot read the file: {}", filename, err);
+                failed = true;
+                continue;
+            },
+        };
+        // Everything between the queries, like separators and comments, is kept as it is
+        let mut formatted = String::new();
+        let mut end = 0;
+        for query in split_queries(&contents) {
+            formatted.push_str(&contents[end..query.offset]);
+            match parse_query_with_comments(query.text) {
+                Ok(parsed) => formatted.push_str(&format_query(&parsed, &options)),
+                Err(err) => {
+                    eprintln!("{}:{}: {}", filename, query.line, within_file(err, &query));
+                    formatted.push_str(query.text);
+                    failed = true;
+                },
+            }
+            end = query.offset + query.text.len();
+        }
+        formatted.push_str(&contents[end..]);
+
+        if write {
+            if let Err(err) = fs::write(&filename, formatted) {
+                eprintln!("{}: error: cannot write the file: {}", filename, err);
+                failed = true;
+            }
+        } else {
+            print!("{}", formatted);
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,316 @@
+//! Whether the documents one find clause matches are also matched by another one
+//!
+//! Results of a query can e.g. be reused for a narrower query. The answers are sound: `Yes` and
+//! `No` are only given if they are proven, everything else is `Unknown`. Both find clauses are
+//! lowered and optimised first, so that it doesn't matter how they were written.
+//!
+//! `Yes` is proven by looking at the structure of the conditions and by comparing the ranges of
+//! comparisons. `No` is proven by a document that one find clause matches, but the other doesn't.
+
+use std::slice;
+
+use serde_json::{self, Map, Value as Json};
+
+use ast::{JsonValue, Path, PathPart, PathPartKind, Predicate, Span, ValueKind};
+use eval::{evaluate_plan, to_json};
+use optimize::{may_fail, optimize_filter};
+use plan::{lower_filter, Filter, Output, Plan};
+use range::Range;
+use score::Bm25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Answer {
+    Yes,
+    No,
+    Unknown,
+}
+
+/// Returns whether every document that matches `narrower` also matches `wider`
+pub fn is_subsumed_by(narrower: &Predicate, wider: &Predicate) -> Answer {
+    subsumed(&normalize(narrower), &normalize(wider))
+}
+
+/// Returns whether both find clauses match the same documents
+pub fn equivalent(aa: &Predicate, bb: &Predicate) -> Answer {
+    let (aa, bb) = (normalize(aa), normalize(bb));
+    match (subsumed(&aa, &bb), subsumed(&bb, &aa)) {
+        (Answer::Yes, Answer::Yes) => Answer::Yes,
+        (Answer::No, _) | (_, Answer::No) => Answer::No,
+        _ => Answer::Unknown,
+    }
+}
+
+fn normalize(predicate: &Predicate) -> Filter {
+    optimize_filter(lower_filter(predicate))
+}
+
+fn subsumed(narrower: &Filter, wider: &Filter) -> Answer {
+    if narrower == wider {
+        Answer::Yes
+    } else if may_fail(narrower) || may_fail(wider) {
+        // The evaluator can't tell what a condition with e.g. a parameter matches
+        Answer::Unknown
+    } else if implies(narrower, wider) {
+        Answer::Yes
+    } else if counterexample(narrower, wider) {
+        Answer::No
+    } else {
+        Answer::Unknown
+    }
+}
+
+/// Whether it's proven that `aa` only matches if `bb` does
+fn implies(aa: &Filter, bb: &Filter) -> bool {
+    if aa == bb || *aa == Filter::Nothing || *bb == Filter::All {
+        return true;
+    }
+    match (aa, bb) {
+        // Boosts only change the score
+        (Filter::Boost(_, aa), _) => return implies(aa, bb),
+        (_, Filter::Boost(_, bb)) => return implies(aa, bb),
+        (Filter::Or(alternatives), _) => return alternatives.iter().all(|aa| implies(aa, bb)),
+        (_, Filter::And(all)) => return all.iter().all(|bb| implies(aa, bb)),
+        (_, Filter::Or(alternatives)) if alternatives.iter().any(|bb| implies(aa, bb)) => {
+            return true
+        },
+        _ => {},
+    }
+
+    let conjuncts = match *aa {
+        Filter::And(ref all) => &all[..],
+        _ => slice::from_ref(aa),
+    };
+    if conjuncts.len() > 1 && conjuncts.iter().any(|aa| implies(aa, bb)) {
+        return true;
+    }
+    // Contradicting comparisons never match
+    let contradiction = conjuncts.iter().any(|filter| match *filter {
+        Filter::Compare(ref path, ..) => range(conjuncts, path).is_empty(),
+        _ => false,
+    });
+    if contradiction {
+        return true;
+    }
+    match *bb {
+        Filter::Compare(ref path, op, ref value) => {
+            let mut wanted = Range::new(to_path(path));
+            wanted.add(op, value, Span::default());
+            range(conjuncts, path).is_within(&wanted)
+        },
+        Filter::Not(ref negated) => {
+            // Values outside of the range never match the comparison
+            let disjoint = match **negated {
+                Filter::Compare(ref path, op, ref value) => {
+                    let mut both = range(conjuncts, path);
+                    both.add(op, value, Span::default());
+                    both.is_empty()
+                },
+                _ => false,
+            };
+            disjoint || conjuncts.iter().any(|aa| match *aa {
+                Filter::Not(ref other) => implies(negated, other),
+                _ => false,
+            })
+        },
+        Filter::Exists(ref path) => conjuncts.iter().any(|aa| requires(aa, path)),
+        Filter::Any(ref path, _, ref wider) => conjuncts.iter().any(|aa| match *aa {
+            Filter::Any(ref other, _, ref narrower) => other == path && implies(narrower, wider),
+            _ => false,
+        }),
+        Filter::Bind(ref path, _, ref wider) => conjuncts.iter().any(|aa| match *aa {
+            Filter::Bind(ref other, _, ref narrower) => other == path && implies(narrower, wider),
+            _ => false,
+        }),
+        _ => false,
+    }
+}
+
+/// The range of the comparisons on the path that all need to match
+fn range(conjuncts: &[Filter], path: &[String]) -> Range {
+    let mut range = Range::new(to_path(path));
+    for filter in conjuncts {
+        if let Filter::Compare(ref other, op, ref value) = *filter {
+            if other[..] == *path {
+                range.add(op, value, Span::default());
+            }
+        }
+    }
+    range
+}
+
+/// Whether the filter only matches if the path exists
+fn requires(filter: &Filter, path: &[String]) -> bool {
+    match *filter {
+        Filter::Exists(ref other) | Filter::Compare(ref other, ..) |
+        Filter::WordMatch(ref other, ..) | Filter::Any(ref other, ..) |
+        Filter::Bind(ref other, ..) => other.starts_with(path),
+        Filter::Boost(_, ref inner) => requires(inner, path),
+        Filter::And(ref all) => all.iter().any(|filter| requires(filter, path)),
+        Filter::Or(ref alternatives) => {
+            alternatives.iter().all(|filter| requires(filter, path))
+        },
+        _ => false,
+    }
+}
+
+fn to_path(keys: &[String]) -> Path {
+    let parts = keys.iter()
+        .map(|key| PathPart::from(PathPartKind::Field(key.clone())))
+        .collect();
+    Path { parts, default: None, span: Span::default() }
+}
+
+/// Whether there is a document that `aa` matches, but `bb` doesn't. The candidates are built
+/// from the comparisons of `aa`, the evaluator decides whether they match.
+fn counterexample(aa: &Filter, bb: &Filter) -> bool {
+    let alternatives = match *aa {
+        Filter::Or(ref alternatives) => &alternatives[..],
+        _ => slice::from_ref(aa),
+    };
+    alternatives.iter().any(|alternative| {
+        let conjuncts = match *alternative {
+            Filter::And(ref all) => &all[..],
+            _ => slice::from_ref(alternative),
+        };
+        match witness(conjuncts) {
+            Some(document) => matches(aa, &document) == Some(true) &&
+                matches(bb, &document) == Some(false),
+            None => false,
+        }
+    })
+}
+
+/// Builds a document with values for all compared and existing paths
+fn witness(conjuncts: &[Filter]) -> Option<Json> {
+    let mut document = Json::Object(Map::new());
+    for filter in conjuncts {
+        match *filter {
+            Filter::Compare(ref path, ..) if !path.is_empty() => {
+                insert(&mut document, path, pick(&range(conjuncts, path))?);
+            },
+            Filter::Exists(ref path) if !path.is_empty() => insert(&mut document, path, Json::Null),
+            _ => {},
+        }
+    }
+    Some(document)
+}
+
+/// Sets the value at the path, unless there's already one
+fn insert(document: &mut Json, path: &[String], value: Json) {
+    let (last, parents) = path.split_last().expect("the path isn't empty");
+    let mut current = document;
+    for key in parents {
+        current = match *current {
+            Json::Object(ref mut object) => {
+                object.entry(key.clone()).or_insert_with(|| Json::Object(Map::new()))
+            },
+            _ => return,
+        };
+    }
+    if let Json::Object(ref mut object) = *current {
+        let existing = object.entry(last.clone()).or_insert(Json::Null);
+        if existing.is_null() {
+            *existing = value;
+        }
+    }
+}
+
+/// Returns a value within the range, if it can easily be found
+fn pick(range: &Range) -> Option<Json> {
+    let (lower, upper) = (range.lower.as_ref(), range.upper.as_ref());
+    match (lower, upper) {
+        (Some(bound), _) | (_, Some(bound)) if bound.inclusive => value(&bound.value.kind),
+        (Some(lower), _) => match (&lower.value.kind, upper.map(|upper| &upper.value.kind)) {
+            (ValueKind::Number(lower), Some(ValueKind::Number(upper))) => {
+                number((lower.as_f64() + upper.as_f64()) / 2.0)
+            },
+            (ValueKind::Number(lower), None) => number(lower.as_f64() + 1.0),
+            (ValueKind::String(lower), None) => Some(Json::String(format!("{}\u{0}", lower))),
+            (ValueKind::Bool(false), _) => Some(Json::Bool(true)),
+            _ => None,
+        },
+        (None, Some(upper)) => match upper.value.kind {
+            ValueKind::Number(ref upper) => number(upper.as_f64() - 1.0),
+            ValueKind::String(ref upper) if !upper.is_empty() => Some(Json::String(String::new())),
+            ValueKind::Bool(true) => Some(Json::Bool(false)),
+            _ => None,
+        },
+        (None, None) => None,
+    }
+}
+
+fn value(kind: &ValueKind) -> Option<Json> {
+    match *kind {
+        ValueKind::Null => Some(Json::Null),
+        ValueKind::Bool(bool) => Some(Json::Bool(bool)),
+        ValueKind::Number(ref number) => Some(to_json(&JsonValue::Number(number.clone()))),
+        ValueKind::String(ref string) => Some(Json::String(string.clone())),
+        ValueKind::Parameter(_) => None,
+    }
+}
+
+fn number(float: f64) -> Option<Json> {
+    serde_json::Number::from_f64(float).map(Json::Number)
+}
+
+/// Whether the filter matches the document, `None` if it can't be evaluated
+fn matches(filter: &Filter, document: &Json) -> Option<bool> {
+    let plan = Plan {
+        filter: filter.clone(),
+        sort: Vec::new(),
+        output: Output::Documents,
+        limit: None,
+    };
+    evaluate_plan(&plan, slice::from_ref(document), &Bm25::default())
+        .ok()
+        .map(|matched| !matched.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use parse_query;
+
+    fn subsumed(narrower: &str, wider: &str) -> Answer {
+        is_subsumed_by(&parse_query(narrower).unwrap().find, &parse_query(wider).unwrap().find)
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(subsumed("find {age: > 20}", "find {age: > 18}"), Answer::Yes);
+        assert_eq!(subsumed("find {age: > 18}", "find {age: > 20}"), Answer::No);
+        assert_eq!(subsumed("find {age: >= 20, age: < 30}", "find {age: > 18, age: <= 30}"),
+                   Answer::Yes);
+        assert_eq!(subsumed("find {a: == \"x\"}", "find {a: != \"y\"}"), Answer::Yes);
+        assert_eq!(subsumed("find {a: == 1}", "find {a: == \"1\"}"), Answer::No);
+        assert_eq!(subsumed("find {a: > 5, a: < 1}", "find {b: == 1}"), Answer::Yes);
+    }
+
+    #[test]
+    fn structure() {
+        assert_eq!(subsumed("find {a: == 1, b: == 2}", "find {a: == 1}"), Answer::Yes);
+        assert_eq!(subsumed("find {a: == 1}", "find {a: == 1, b: == 2}"), Answer::No);
+        assert_eq!(subsumed("find {a: == 1}", "find {a: == 1} || {b: == 2}"), Answer::Yes);
+        assert_eq!(subsumed("find {a: {b: == 1, c: == 2}}", "find {a: {b: >= 1}}"), Answer::Yes);
+        assert_eq!(subsumed("find {a: [{b: == 1, c: == 2}]}", "find {a: x::[{b: == 1}]}"),
+                   Answer::Yes);
+        assert_eq!(subsumed("find {a: !{b: == 1}}", "find !{a: {b: == 1}}"), Answer::Yes);
+        assert_eq!(subsumed("find {a: == 1}", "find {}"), Answer::Yes);
+        assert_eq!(subsumed("find {a: ~= \"x\"}", "find {a: ~= \"x y\"}"), Answer::Unknown);
+        assert_eq!(subsumed("find {a: == @p}", "find {a: == 1}"), Answer::Unknown);
+    }
+
+    #[test]
+    fn equivalence() {
+        let equivalent_to = |aa: &str, bb: &str| {
+            equivalent(&parse_query(aa).unwrap().find, &parse_query(bb).unwrap().find)
+        };
+        assert_eq!(equivalent_to("find {a: {b: == 1}, c: > 2}", "find {c: > 2} && {a: {b: == 1}}"),
+                   Answer::Yes);
+        assert_eq!(equivalent_to("find {a: >= 1, a: <= 1}", "find {a: == 1}"), Answer::Yes);
+        assert_eq!(equivalent_to("find !({a: == 1} || {b: == 1})", "find {a: != 1, b: != 1}"),
+                   Answer::Yes);
+        assert_eq!(equivalent_to("find {a: == 1}", "find {a: == 1, b: == 1}"), Answer::No);
+    }
+}
@@ -0,0 +1,331 @@
+use std::error::Error;
+use std::fmt;
+
+use lalrpop_util::ParseError;
+
+use ast::Span;
+use lexer::Tok;
+
+/// Stable identifiers for the kinds of errors the parser reports
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    InvalidToken,
+    UnexpectedToken,
+    UnexpectedEnd,
+    ExtraToken,
+    InvalidBbox,
+    NumberTooLarge,
+    InvalidEscape,
+    NumberOutOfRange,
+}
+
+impl ErrorCode {
+    /// The code as it is shown to users and written to logs, e.g. `E0002`
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ErrorCode::InvalidToken => "E0001",
+            ErrorCode::UnexpectedToken => "E0002",
+            ErrorCode::UnexpectedEnd => "E0003",
+            ErrorCode::ExtraToken => "E0004",
+            ErrorCode::InvalidBbox => "E0005",
+            ErrorCode::NumberTooLarge => "E0006",
+            ErrorCode::InvalidEscape => "E0007",
+            ErrorCode::NumberOutOfRange => "E0008",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An error raised by the grammar actions when the query is syntactically valid, but doesn't
+/// make sense, e.g. the bounding box operator `&&` followed by a number
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrammarError {
+    pub code: ErrorCode,
+    /// The byte range of the value that was rejected
+    pub span: Span,
+    pub message: String,
+}
+
+impl GrammarError {
+    pub fn new(code: ErrorCode, span: Span, message: String) -> GrammarError {
+        GrammarError { code, span, message }
+    }
+}
+
+impl fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error[{}]: {}", self.code, self.message)
+    }
+}
+
+impl Error for GrammarError {}
+
+/// An error that occurred while parsing a query
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// The byte range of the offending token within the query
+    pub span: Span,
+    /// The line (starting at 1) where the error occurred
+    pub line: usize,
+    /// The column (starting at 1, counted in characters) where the error occurred
+    pub column: usize,
+    /// The text of the offending token, if there was one
+    pub token: Option<String>,
+    /// Human readable descriptions of what would have been valid instead
+    pub expected: Vec<String>,
+}
+
+impl NoiseError {
+    pub fn new(code: ErrorCode, message: String, input: &str, span: Span) -> NoiseError {
+        let (line, column) = line_column(input, span.start);
+        NoiseError {
+            code,
+            message,
+            span,
+            line,
+            column,
+            token: None,
+            expected: Vec::new(),
+        }
+    }
+
+    /// Converts the error lalrpop returns into a `NoiseError`. `input` is the query that was
+    /// parsed, it's needed to determine the line and column.
+    pub fn from_parse_error(input: &str, error: ParseError<usize, Tok, GrammarError>)
+                            -> NoiseError {
+        match error {
+            ParseError::InvalidToken { location } => {
+                let end = input[location..].chars().next()
+                    .map_or(location, |cc| location + cc.len_utf8());
+                let token = &input[location..end];
+                let mut error = NoiseError::new(ErrorCode::InvalidToken,
+                                                format!("invalid token `{}`", token),
+                                                input,
+                                                Span::new(location, end));
+                error.token = Some(token.to_string());
+                error
+            },
+            ParseError::UnrecognizedToken { token: Some((start, _, end)), expected } => {
+                let token = &input[start..end];
+                let mut error = NoiseError::new(ErrorCode::UnexpectedToken,
+                                                format!("unexpected `{}`", token),
+                                                input,
+                                                Span::new(start, end));
+                error.token = Some(token.to_string());
+                error.expected = describe_expected(&expected);
+                error
+            },
+            ParseError::UnrecognizedToken { token: None, expected } => {
+                let mut error = NoiseError::new(ErrorCode::UnexpectedEnd,
+                                                "unexpected end of query".to_string(),
+                                                input,
+                                                Span::new(input.len(), input.len()));
+                error.expected = describe_expected(&expected);
+                error
+            },
+            ParseError::ExtraToken { token: (start, _, end) } => {
+                let token = &input[start..end];
+                let message = format!("unexpected `{}` after the end of the query", token);
+                let mut error = NoiseError::new(ErrorCode::ExtraToken,
+                                                message,
+                                                input,
+                                                Span::new(start, end));
+                error.token = Some(token.to_string());
+                error
+            },
+            ParseError::User { error } => {
+                let mut noise_error = NoiseError::new(error.code, error.message, input, error.span);
+                noise_error.token = Some(input[error.span.start..error.span.end].to_string());
+                noise_error
+            },
+        }
+    }
+}
+
+impl fmt::Display for NoiseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error[{}]: {} at line {}, column {}", self.code, self.message, self.line,
+               self.column)?;
+        match self.expected.len() {
+            0 => Ok(()),
+            1 => write!(f, ", expected {}", self.expected[0]),
+            len => {
+                write!(f, ", expected one of {}", self.expected[..len - 1].join(", "))?;
+                write!(f, " or {}", self.expected[len - 1])
+            },
+        }
+    }
+}
+
+impl Error for NoiseError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Returns the line and the column (both starting at 1) of a byte offset
+fn line_column(input: &str, offset: usize) -> (usize, usize) {
+    let before = &input[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+// Keywords that are also valid field names. If a field name is expected, they are covered by it
+// and don't need to be listed separately.
+const FIELD_KEYWORDS: &[&str] = &[
+    "find", "order", "return", "limit", "asc", "desc", "array", "array_flat", "avg", "count",
+    "concat", "group", "max", "max_array", "min", "min_array", "score", "sum",
+];
+
+const OPERATORS: &[&str] = &["==", ">", ">=", "<", "<=", "!=", "!~=", "!~", "~=", "~"];
+
+// The terminals of the tokens that carry a value and how they are described to users. Keywords and
+// operators are shown as they are, before those.
+const VALUE_TERMINALS: &[(&str, &str)] = &[
+    ("\"String\"", "a string"),
+    ("\"Number\"", "a number"),
+    ("\"Integer\"", "a number"),
+    ("\"Parameter\"", "a parameter like @name"),
+    ("\"DotField\"", "a path like .field"),
+    ("\"Field\"", "a field name"),
+];
+
+/// Turns the terminal names lalrpop reports into words a user understands
+fn describe_expected(expected: &[String]) -> Vec<String> {
+    let expects_field = expected.iter().any(|terminal| terminal == "\"Field\"");
+
+    let mut described: Vec<String> = Vec::new();
+    for terminal in expected {
+        if VALUE_TERMINALS.iter().any(|&(name, _)| name == terminal) {
+            continue;
+        }
+        let literal = terminal.trim_matches('"');
+        let description = if OPERATORS.contains(&literal) {
+            "an operator like ==".to_string()
+        } else if expects_field && FIELD_KEYWORDS.contains(&literal) {
+            continue;
+        } else {
+            format!("`{}`", literal)
+        };
+        if !described.contains(&description) {
+            described.push(description);
+        }
+    }
+    for &(name, description) in VALUE_TERMINALS {
+        if expected.iter().any(|terminal| terminal == name) &&
+            !described.iter().any(|existing| existing == description) {
+            described.push(description.to_string());
+        }
+    }
+    described
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use lexer::Lexer;
+    use noise;
+
+    fn parse(input: &str) -> NoiseError {
+        noise::parse_Noise(Lexer::new(input))
+            .map_err(|err| NoiseError::from_parse_error(input, err))
+            .unwrap_err()
+    }
+
+    fn parse_ok(input: &str) -> bool {
+        noise::parse_Noise(Lexer::new(input)).is_ok()
+    }
+
+    #[test]
+    fn unexpected_token() {
+        let error = parse(r#"find {hello: world}"#);
+        assert_eq!(error.code, ErrorCode::UnexpectedToken);
+        assert_eq!(error.span, Span::new(18, 19));
+        assert_eq!((error.line, error.column), (1, 19));
+        assert_eq!(error.token, Some("}".to_string()));
+        assert_eq!(error.expected, vec!["`::`"]);
+        assert_eq!(error.to_string(),
+                   "error[E0002]: unexpected `}` at line 1, column 19, expected `::`");
+    }
+
+    #[test]
+    fn unexpected_end() {
+        let error = parse("find {hello:\n  == ");
+        assert_eq!(error.code, ErrorCode::UnexpectedEnd);
+        assert_eq!(error.span, Span::new(18, 18));
+        assert_eq!((error.line, error.column), (2, 6));
+        assert_eq!(error.token, None);
+        assert_eq!(error.expected,
+                   vec!["`false`", "`null`", "`true`", "a string", "a number",
+                        "a parameter like @name"]);
+    }
+
+    #[test]
+    fn expected_field_names_and_operators() {
+        let error = parse(r#"find {"a": == 1, }"#);
+        assert_eq!(error.code, ErrorCode::UnexpectedToken);
+        assert!(error.expected.contains(&"a field name".to_string()));
+        assert!(error.expected.contains(&"an operator like ==".to_string()));
+        assert!(!error.expected.contains(&"`find`".to_string()));
+    }
+
+    #[test]
+    fn bbox_operator_without_bbox() {
+        let error = parse(r#"find {loc: && 5}"#);
+        assert_eq!(error.code, ErrorCode::InvalidBbox);
+        assert_eq!(error.span, Span::new(14, 15));
+        assert_eq!(error.token, Some("5".to_string()));
+        assert_eq!(error.to_string(),
+                   "error[E0005]: the bounding box operator `&&` needs a bounding box \
+                    [W, S, E, N] as value at line 1, column 15");
+
+        let error = parse(r#"find {loc: && @area}"#);
+        assert_eq!(error.code, ErrorCode::InvalidBbox);
+        assert_eq!(error.token, Some("@area".to_string()));
+    }
+
+    #[test]
+    fn number_too_large() {
+        let error = parse(r#"find {} limit 99999999999999999999"#);
+        assert_eq!(error.code, ErrorCode::NumberTooLarge);
+        assert_eq!(error.span, Span::new(14, 34));
+
+        assert!(parse_ok(r#"find {a: == 99999999999999999999}"#));
+
+        let error = parse(r#"find {a: == -1e400}"#);
+        assert_eq!(error.code, ErrorCode::NumberOutOfRange);
+        assert_eq!(error.span, Span::new(12, 18));
+
+        let error = parse(r#"find {a: == 1e-400}"#);
+        assert_eq!(error.code, ErrorCode::NumberOutOfRange);
+        assert_eq!(error.span, Span::new(12, 18));
+        assert!(parse_ok(r#"find {a: == 0.000e-400}"#));
+    }
+
+    #[test]
+    fn invalid_escape() {
+        let error = parse(r#"find {a: == "tab\tand \q"}"#);
+        assert_eq!(error.code, ErrorCode::InvalidEscape);
+        assert_eq!(error.span, Span::new(22, 24));
+        assert_eq!(error.token, Some("\\q".to_string()));
+        assert_eq!(error.to_string(), "error[E0007]: invalid escape `\\q` at line 1, column 23");
+    }
+
+    #[test]
+    fn invalid_token() {
+        let error = parse("find {a: == 1} %");
+        assert_eq!(error.code, ErrorCode::InvalidToken);
+        assert_eq!(error.span, Span::new(15, 16));
+        assert_eq!(error.token, Some("%".to_string()));
+    }
+}
@@ -0,0 +1,144 @@
+//! Conversion between string literals as they are written within a query and their values
+//!
+//! The escapes are the ones of JSON, so that keys and values of a query match the strings stored
+//! in the documents.
+
+use std::fmt;
+use std::str::CharIndices;
+
+use ast::Span;
+use error::{ErrorCode, GrammarError};
+
+/// Returns the value of a string literal given without its quotes. `offset` is the byte offset
+/// of the literal within the query, it's used for the spans of errors.
+pub fn unescape(literal: &str, offset: usize) -> Result<String, GrammarError> {
+    let mut unescaped = String::with_capacity(literal.len());
+    let mut chars = literal.char_indices();
+    while let Some((start, cc)) = chars.next() {
+        if cc != '\\' {
+            unescaped.push(cc);
+            continue;
+        }
+        let error = |end: usize, message: String| {
+            GrammarError::new(ErrorCode::InvalidEscape,
+                              Span::new(offset + start, offset + end),
+                              message)
+        };
+        let escaped = match chars.next() {
+            Some((_, escaped)) => escaped,
+            None => return Err(error(literal.len(), "incomplete escape `\\`".to_string())),
+        };
+        let end = start + 1 + escaped.len_utf8();
+        unescaped.push(match escaped {
+            '"' => '"',
+            '\\' => '\\',
+            '/' => '/',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let high = hex(literal, &mut chars)
+                    .ok_or_else(|| error(end, "`\\u` needs four hex digits".to_string()))?;
+                let end = end + 4;
+                match high {
+                    0xd800..=0xdbff => {
+                        let low = match (chars.next(), chars.next()) {
+                            (Some((_, '\\')), Some((_, 'u'))) => hex(literal, &mut chars),
+                            _ => None,
+                        };
+                        match low {
+                            Some(low @ 0xdc00..=0xdfff) => {
+                                let code = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+                                char::from_u32(code).expect("surrogate pairs are valid")
+                            },
+                            _ => {
+                                return Err(error(end, format!(
+                                    "`{}` must be followed by a low surrogate `\\uDC00` to \
+                                     `\\uDFFF`", &literal[start..end])))
+                            },
+                        }
+                    },
+                    0xdc00..=0xdfff => {
+                        return Err(error(end, format!("`{}` is a low surrogate without a high \
+                                                       surrogate before it",
+                                                      &literal[start..end])))
+                    },
+                    _ => char::from_u32(high).expect("non-surrogates are valid"),
+                }
+            },
+            _ => return Err(error(end, format!("invalid escape `{}`", &literal[start..end]))),
+        });
+    }
+    Ok(unescaped)
+}
+
+/// Parses the four hex digits of a `\u` escape
+fn hex(literal: &str, chars: &mut CharIndices) -> Option<u32> {
+    let start = chars.clone().next()?.0;
+    let digits = literal.get(start..start + 4)?;
+    if !digits.chars().all(|cc| cc.is_ascii_hexdigit()) {
+        return None;
+    }
+    chars.nth(3);
+    u32::from_str_radix(digits, 16).ok()
+}
+
+/// Displays a string with everything escaped that can't be within a string literal as is. The
+/// quotes aren't added.
+pub struct Escaped<'a>(pub &'a str);
+
+impl<'a> fmt::Display for Escaped<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for cc in self.0.chars() {
+            match cc {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\u{8}' => f.write_str("\\b")?,
+                '\u{c}' => f.write_str("\\f")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                cc if cc.is_control() => write!(f, "\\u{:04x}", cc as u32)?,
+                cc => write!(f, "{}", cc)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescape_valid() {
+        assert_eq!(unescape(r#"plain"#, 0), Ok("plain".to_string()));
+        assert_eq!(unescape(r#"\"\\\/\b\f\n\r\t"#, 0), Ok("\"\\/\u{8}\u{c}\n\r\t".to_string()));
+        assert_eq!(unescape(r#"caf\u00e9 \u00C9"#, 0), Ok("café É".to_string()));
+        assert_eq!(unescape(r#"\ud83d\ude00!"#, 0), Ok("😀!".to_string()));
+    }
+
+    #[test]
+    fn unescape_invalid() {
+        let error = unescape(r#"ab\x"#, 10).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidEscape);
+        assert_eq!(error.span, Span::new(12, 14));
+        assert_eq!(error.message, "invalid escape `\\x`");
+
+        assert_eq!(unescape(r#"\é"#, 0).unwrap_err().span, Span::new(0, 3));
+        assert_eq!(unescape(r#"\u12"#, 0).unwrap_err().span, Span::new(0, 2));
+        assert_eq!(unescape(r#"\u12g4"#, 0).unwrap_err().span, Span::new(0, 2));
+        assert_eq!(unescape(r#"\ud83d"#, 0).unwrap_err().span, Span::new(0, 6));
+        assert_eq!(unescape(r#"\ud83dA"#, 0).unwrap_err().span, Span::new(0, 6));
+        assert_eq!(unescape(r#"a\ude00"#, 0).unwrap_err().span, Span::new(1, 7));
+    }
+
+    #[test]
+    fn escape() {
+        let string = "\"quoted\" \\ é\n\u{1}";
+        assert_eq!(Escaped(string).to_string(), r#"\"quoted\" \\ é\n\u0001"#);
+        assert_eq!(unescape(&Escaped(string).to_string(), 0), Ok(string.to_string()));
+    }
+}
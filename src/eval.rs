@@ -0,0 +1,579 @@
+//! Runs queries against a collection of JSON documents in memory
+//!
+//! This is meant for testing queries and for running them on small datasets, there's no index
+//! involved, every document is matched against the find clause.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+use serde_json::{self, Value as Json};
+
+use aggregate::aggregate;
+use ast::{JsonNumber, JsonValue, Path, PathPart, PathPartKind, Projection, ProjectionKind, Query,
+          Value, ValueKind};
+use optimize::optimize;
+use plan::{self, CompareOp, Direction, Filter, Output, Plan, SortKey};
+use score::{Bm25, Scorer, Statistics};
+use text::{tokenize, Phrase};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EvalError {
+    /// The query contains a parameter that wasn't replaced by a value
+    UnboundParameter(String),
+    /// The query uses something the evaluator can't do
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EvalError::UnboundParameter(ref name) => write!(f, "parameter @{} has no value", name),
+            EvalError::Unsupported(what) => write!(f, "{} is not supported", what),
+        }
+    }
+}
+
+impl Error for EvalError {}
+
+/// The array elements that were bound to variables while matching a single document
+type Bindings = HashMap<String, Vec<Json>>;
+
+/// Returns the documents matching the query, ordered, projected and limited as the query says.
+/// Word matches are scored with `Bm25`.
+pub fn evaluate(query: &Query, documents: &[Json]) -> Result<Vec<Json>, EvalError> {
+    evaluate_with(query, documents, &Bm25::default())
+}
+
+/// Like `evaluate()`, but word matches are scored with the given scorer
+pub fn evaluate_with(query: &Query, documents: &[Json], scorer: &dyn Scorer)
+                     -> Result<Vec<Json>, EvalError> {
+    evaluate_plan(&optimize(plan::lower(query)), documents, scorer)
+}
+
+/// Runs the plan of a query
+pub fn evaluate_plan(plan: &Plan, documents: &[Json], scorer: &dyn Scorer)
+                     -> Result<Vec<Json>, EvalError> {
+    let statistics = Statistics::new(documents);
+    let mut matches = Vec::new();
+    for document in documents {
+        let mut matcher = Matcher {
+            scorer,
+            statistics: &statistics,
+            bindings: Bindings::new(),
+        };
+        if let Some(score) = matcher.filter(&plan.filter, document, &mut Vec::new())? {
+            matches.push(Match {
+                document,
+                bindings: matcher.bindings,
+                score,
+            });
+        }
+    }
+
+    if !plan.sort.is_empty() {
+        let mut keyed = Vec::with_capacity(matches.len());
+        for matched in matches {
+            let keys: Vec<Json> = plan.sort
+                .iter()
+                .map(|key| match *key {
+                    SortKey::Path(ref path, _) => lookup(matched.document, path),
+                    SortKey::Score(_) => score_to_json(matched.score),
+                })
+                .collect();
+            keyed.push((keys, matched));
+        }
+        keyed.sort_by(|aa, bb| {
+            for (ii, key) in plan.sort.iter().enumerate() {
+                let ordering = match key.direction() {
+                    Direction::Descending => collate(&bb.0[ii], &aa.0[ii]),
+                    Direction::Ascending => collate(&aa.0[ii], &bb.0[ii]),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+        matches = keyed.into_iter().map(|(_, matched)| matched).collect();
+    }
+
+    if let Output::Aggregate(ref projection) = plan.output {
+        let documents: Vec<&Json> = matches.iter().map(|matched| matched.document).collect();
+        let mut results = aggregate(projection, &documents)?;
+        if let Some(limit) = plan.limit {
+            results.truncate(limit as usize);
+        }
+        return Ok(results);
+    }
+
+    if let Some(limit) = plan.limit {
+        matches.truncate(limit as usize);
+    }
+
+    matches.iter()
+        .map(|matched| match plan.output {
+            Output::Project(ref projection) => project(projection, matched),
+            _ => Ok(matched.document.clone()),
+        })
+        .collect()
+}
+
+/// A document that matched the find clause
+struct Match<'a> {
+    document: &'a Json,
+    bindings: Bindings,
+    score: f64,
+}
+
+fn score_to_json(score: f64) -> Json {
+    serde_json::Number::from_f64(score).map_or(Json::Null, Json::Number)
+}
+
+/// Returns the value the keys lead to
+fn field<'a>(value: &'a Json, path: &[String]) -> Option<&'a Json> {
+    path.iter().try_fold(value, |value, key| value.as_object().and_then(|object| object.get(key)))
+}
+
+/// Matches the find clause against a single document
+struct Matcher<'a> {
+    scorer: &'a dyn Scorer,
+    statistics: &'a Statistics,
+    bindings: Bindings,
+}
+
+impl<'a> Matcher<'a> {
+    /// Returns the score if the filter matches. `scope` are the keys that lead from the document
+    /// to the value.
+    fn filter(&mut self, filter: &Filter, value: &Json, scope: &mut Vec<String>)
+              -> Result<Option<f64>, EvalError> {
+        Ok(match *filter {
+            Filter::All => Some(0.0),
+            Filter::Nothing => None,
+            Filter::Exists(ref path) => unscored(field(value, path).is_some()),
+            Filter::Compare(ref path, op, ref expected) => {
+                let ordering = compare_field(value, path, expected)?;
+                unscored(match op {
+                    CompareOp::Equal => ordering == Some(Ordering::Equal),
+                    CompareOp::Greater => ordering == Some(Ordering::Greater),
+                    CompareOp::GreaterEqual => {
+                        matches!(ordering, Some(Ordering::Greater) | Some(Ordering::Equal))
+                    },
+                    CompareOp::Less => ordering == Some(Ordering::Less),
+                    CompareOp::LessEqual => {
+                        matches!(ordering, Some(Ordering::Less) | Some(Ordering::Equal))
+                    },
+                })
+            },
+            Filter::WordMatch(ref path, distance, ref phrase) => {
+                match (field(value, path), &phrase.kind) {
+                    (_, ValueKind::Parameter(name)) => {
+                        return Err(EvalError::UnboundParameter(name.clone()))
+                    },
+                    (Some(Json::String(text)), ValueKind::String(phrase)) => {
+                        let phrase = Phrase::new(phrase, distance);
+                        let words = tokenize(text);
+                        if phrase.matches_words(&words) {
+                            let depth = scope.len();
+                            scope.extend(path.iter().cloned());
+                            let score = self.statistics
+                                .field(scope)
+                                .map_or(0.0, |field| self.scorer.score(&phrase, &words, field));
+                            scope.truncate(depth);
+                            Some(score)
+                        } else {
+                            None
+                        }
+                    },
+                    _ => None,
+                }
+            },
+            Filter::Intersect(..) => return Err(EvalError::Unsupported("bounding box (`&&`)")),
+            Filter::Any(ref path, ref name, ref inner) => {
+                let (elements, score) = match field(value, path) {
+                    Some(nested) => self.scoped(path, scope, |matcher, scope| {
+                        matcher.elements(inner, nested, scope)
+                    })?,
+                    None => (Vec::new(), None),
+                };
+                if let Some(ref name) = *name {
+                    self.bind(name, elements);
+                }
+                score
+            },
+            Filter::Bind(ref path, ref name, ref inner) => {
+                let (elements, score) = match field(value, path) {
+                    Some(nested) => self.scoped(path, scope, |matcher, scope| {
+                        Ok(match matcher.filter(inner, nested, scope)? {
+                            Some(score) => (vec![nested], Some(score)),
+                            None => (Vec::new(), None),
+                        })
+                    })?,
+                    None => (Vec::new(), None),
+                };
+                self.bind(name, elements);
+                score
+            },
+            Filter::Not(ref inner) => {
+                // Variables bound within a negation never have a match
+                let bindings = mem::take(&mut self.bindings);
+                let score = self.filter(inner, value, scope);
+                self.bindings = bindings;
+                match score? {
+                    Some(_) => None,
+                    None => Some(0.0),
+                }
+            },
+            Filter::Boost(boost, ref inner) => {
+                self.filter(inner, value, scope)?.map(|score| score * boost as f64)
+            },
+            Filter::And(ref filters) => {
+                let mut total = 0.0;
+                for filter in filters {
+                    match self.filter(filter, value, scope)? {
+                        Some(score) => total += score,
+                        None => return Ok(None),
+                    }
+                }
+                Some(total)
+            },
+            Filter::Or(ref filters) => {
+                // All filters are evaluated so that variables are bound from all of them
+                let mut total = None;
+                for filter in filters {
+                    if let Some(score) = self.filter(filter, value, scope)? {
+                        total = Some(total.unwrap_or(0.0) + score);
+                    }
+                }
+                total
+            },
+        })
+    }
+
+    /// Runs `inner` with the scope extended by the path
+    fn scoped<T, F>(&mut self, path: &[String], scope: &mut Vec<String>, inner: F) -> T
+        where F: FnOnce(&mut Matcher<'a>, &mut Vec<String>) -> T
+    {
+        let depth = scope.len();
+        scope.extend(path.iter().cloned());
+        let result = inner(self, scope);
+        scope.truncate(depth);
+        result
+    }
+
+    fn bind(&mut self, name: &str, elements: Vec<&Json>) {
+        self.bindings.entry(name.to_string()).or_default().extend(elements.into_iter().cloned());
+    }
+
+    /// Returns the elements of an array that match the filter and their total score
+    fn elements<'b>(&mut self, filter: &Filter, value: &'b Json, scope: &mut Vec<String>)
+                    -> Result<(Vec<&'b Json>, Option<f64>), EvalError> {
+        let mut matching = Vec::new();
+        let mut total = None;
+        if let Some(elements) = value.as_array() {
+            for element in elements {
+                if let Some(score) = self.filter(filter, element, scope)? {
+                    matching.push(element);
+                    total = Some(total.unwrap_or(0.0) + score);
+                }
+            }
+        }
+        Ok((matching, total))
+    }
+}
+
+/// Conditions other than word matches don't contribute to the score
+fn unscored(matched: bool) -> Option<f64> {
+    if matched {
+        Some(0.0)
+    } else {
+        None
+    }
+}
+
+/// Compares the value of a field with the value given in the query. It returns `None` if the
+/// field doesn't exist or if the values are of different types.
+fn compare_field(value: &Json, path: &[String], expected: &Value)
+                 -> Result<Option<Ordering>, EvalError> {
+    let actual = match field(value, path) {
+        Some(actual) => actual,
+        None => return Ok(None),
+    };
+    Ok(match (actual, &expected.kind) {
+        (_, ValueKind::Parameter(name)) => return Err(EvalError::UnboundParameter(name.clone())),
+        (Json::Null, ValueKind::Null) => Some(Ordering::Equal),
+        (Json::Bool(actual), ValueKind::Bool(expected)) => Some(actual.cmp(expected)),
+        (Json::Number(actual), ValueKind::Number(expected)) => number(actual).compare(expected),
+        (Json::String(actual), ValueKind::String(expected)) => {
+            Some(actual.as_str().cmp(expected.as_str()))
+        },
+        _ => None,
+    })
+}
+
+/// The position of a JSON type within the sort order
+fn type_rank(value: &Json) -> u8 {
+    match *value {
+        Json::Null => 0,
+        Json::Bool(false) => 1,
+        Json::Bool(true) => 2,
+        Json::Number(_) => 3,
+        Json::String(_) => 4,
+        Json::Array(_) => 5,
+        Json::Object(_) => 6,
+    }
+}
+
+/// Compares two JSON values. Values of different types are ordered as null < false < true <
+/// numbers < strings < arrays < objects.
+pub fn collate(aa: &Json, bb: &Json) -> Ordering {
+    match (aa, bb) {
+        (Json::Number(aa), Json::Number(bb)) => {
+            number(aa).compare(&number(bb)).unwrap_or(Ordering::Equal)
+        },
+        (Json::String(aa), Json::String(bb)) => aa.cmp(bb),
+        (Json::Array(aa), Json::Array(bb)) => {
+            for (aa, bb) in aa.iter().zip(bb.iter()) {
+                let ordering = collate(aa, bb);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            aa.len().cmp(&bb.len())
+        },
+        (Json::Object(aa), Json::Object(bb)) => {
+            for ((aa_key, aa_value), (bb_key, bb_value)) in aa.iter().zip(bb.iter()) {
+                let ordering = aa_key.cmp(bb_key).then_with(|| collate(aa_value, bb_value));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            aa.len().cmp(&bb.len())
+        },
+        _ => type_rank(aa).cmp(&type_rank(bb)),
+    }
+}
+
+/// Returns the value the path points to, or the default value (`null` if there's none)
+pub fn lookup(value: &Json, path: &Path) -> Json {
+    lookup_existing(value, path).unwrap_or(Json::Null)
+}
+
+/// Returns the value the path points to, or the default value. It's `None` if the path doesn't
+/// exist and there's no default value.
+pub fn lookup_existing(value: &Json, path: &Path) -> Option<Json> {
+    lookup_parts(value, &path.parts).or_else(|| path.default.as_ref().map(to_json))
+}
+
+fn lookup_parts(value: &Json, parts: &[PathPart]) -> Option<Json> {
+    let (first, rest) = match parts.split_first() {
+        Some(split) => split,
+        None => return Some(value.clone()),
+    };
+    match first.kind {
+        PathPartKind::Field(ref key) => {
+            value.get(key).and_then(|nested| lookup_parts(nested, rest))
+        },
+        PathPartKind::Index(index) => {
+            value.get(index as usize).and_then(|nested| lookup_parts(nested, rest))
+        },
+        PathPartKind::Array => value.as_array().map(|elements| {
+            Json::Array(elements.iter().filter_map(|element| lookup_parts(element, rest)).collect())
+        }),
+    }
+}
+
+/// Converts a number of a document into the type numbers of a query have
+fn number(number: &serde_json::Number) -> JsonNumber {
+    match (number.as_u64(), number.as_i64()) {
+        (Some(uint), _) => JsonNumber::UInt(uint),
+        (None, Some(int)) => JsonNumber::Int(int),
+        _ => JsonNumber::Float(number.as_f64().unwrap_or(f64::NAN)),
+    }
+}
+
+/// Converts a JSON value of the query into a `serde_json` one
+pub fn to_json(value: &JsonValue) -> Json {
+    match *value {
+        JsonValue::Null => Json::Null,
+        JsonValue::Bool(bool) => Json::Bool(bool),
+        JsonValue::Number(JsonNumber::Int(int)) => Json::from(int),
+        JsonValue::Number(JsonNumber::UInt(uint)) => Json::from(uint),
+        JsonValue::Number(ref number) => {
+            serde_json::Number::from_f64(number.as_f64()).map_or(Json::Null, Json::Number)
+        },
+        JsonValue::String(ref string) => Json::String(string.clone()),
+        JsonValue::Array(ref values) => Json::Array(values.iter().map(to_json).collect()),
+        JsonValue::Object(ref members) => {
+            Json::Object(members.iter().map(|(key, value)| (key.clone(), to_json(value))).collect())
+        },
+    }
+}
+
+fn project(projection: &Projection, matched: &Match) -> Result<Json, EvalError> {
+    let document = matched.document;
+    Ok(match projection.kind {
+        ProjectionKind::All => document.clone(),
+        ProjectionKind::Value(ref value) => to_json(value),
+        ProjectionKind::Path(ref path) => lookup(document, path),
+        ProjectionKind::Bind(ref name, ref path) => {
+            let elements = matched.bindings.get(&name.name)
+                .map_or(&[][..], |elements| &elements[..]);
+            Json::Array(elements.iter()
+                        .map(|element| match *path {
+                            Some(ref path) => lookup(element, path),
+                            None => element.clone(),
+                        })
+                        .collect())
+        },
+        ProjectionKind::Object(ref members) => {
+            let mut object = serde_json::Map::new();
+            for (key, value) in members {
+                object.insert(key.name.clone(), project(value, matched)?);
+            }
+            Json::Object(object)
+        },
+        ProjectionKind::Array(ref values) => {
+            let mut array = Vec::with_capacity(values.len());
+            for value in values {
+                array.push(project(value, matched)?);
+            }
+            Json::Array(array)
+        },
+        ProjectionKind::Aggregate(_) => unreachable!("aggregates are handled by `aggregate()`"),
+        ProjectionKind::Score => score_to_json(matched.score),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use parse_query;
+    use score::FieldStats;
+
+    fn documents() -> Vec<Json> {
+        serde_json::from_str(r#"[
+            {"_id": "1", "name": "Alice", "age": 30, "address": {"city": "Berlin"},
+             "tags": ["admin", "dev"], "pets": [{"kind": "cat", "age": 3}],
+             "bio": "Writes Rust code at night"},
+            {"_id": "2", "name": "Bob", "age": 25, "address": {"city": "Paris"},
+             "tags": ["dev"], "pets": [{"kind": "dog", "age": 5}, {"kind": "cat", "age": 1}],
+             "bio": "Loves writing Rust, mostly at night"},
+            {"_id": "3", "name": "Carol", "age": 35, "address": {"city": "Berlin"},
+             "tags": [], "active": true}
+        ]"#).unwrap()
+    }
+
+    fn run(query: &str) -> Result<Json, EvalError> {
+        evaluate(&parse_query(query).unwrap(), &documents()).map(Json::Array)
+    }
+
+    fn ids(query: &str) -> Json {
+        run(&format!("{} return ._id", query)).unwrap()
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(ids(r#"find {name: == "Bob"}"#), json!(["2"]));
+        assert_eq!(ids(r#"find {age: > 25}"#), json!(["1", "3"]));
+        assert_eq!(ids(r#"find {age: >= 25, age: < 35}"#), json!(["1", "2"]));
+        assert_eq!(ids(r#"find {age: <= 30 || active: == true}"#), json!(["1", "2", "3"]));
+        assert_eq!(ids(r#"find {name: != "Bob"}"#), json!(["1", "3"]));
+        assert_eq!(ids(r#"find {name: > 30}"#), json!([]));
+        assert_eq!(ids(r#"find {}"#), json!(["1", "2", "3"]));
+    }
+
+    #[test]
+    fn large_numbers() {
+        let documents = json!([{"id": 9007199254740992u64}, {"id": 9007199254740993u64},
+                               {"id": -9223372036854775807i64}]);
+        let documents = documents.as_array().unwrap();
+        let found = |query| evaluate(&parse_query(query).unwrap(), documents).unwrap();
+        assert_eq!(found("find {id: == 9007199254740993} return .id"),
+                   vec![json!(9007199254740993u64)]);
+        assert_eq!(found("find {id: > 9007199254740992} return .id"),
+                   vec![json!(9007199254740993u64)]);
+        assert_eq!(found("find {id: < -9223372036854775806} return .id"),
+                   vec![json!(-9223372036854775807i64)]);
+        assert_eq!(found("find {} order .id desc return .id limit 1"),
+                   vec![json!(9007199254740993u64)]);
+    }
+
+    #[test]
+    fn nested_objects_and_arrays() {
+        assert_eq!(ids(r#"find {address: {city: == "Berlin"}}"#), json!(["1", "3"]));
+        assert_eq!(ids(r#"find {tags: [== "admin"]}"#), json!(["1"]));
+        assert_eq!(ids(r#"find {pets: [{kind: == "cat", age: < 2}]}"#), json!(["2"]));
+        assert_eq!(ids(r#"find {pets: ![{kind: == "dog"}]}"#), json!(["1"]));
+    }
+
+    #[test]
+    fn order_return_limit() {
+        assert_eq!(run(r#"find {} order .age desc return {name: .name, city: .address.city}
+                          limit 2"#).unwrap(),
+                   json!([{"name": "Carol", "city": "Berlin"},
+                          {"name": "Alice", "city": "Berlin"}]));
+        assert_eq!(run(r#"find {} order .address.city, .name desc return [.name, .pets[0].kind,
+                          .active default=false]"#).unwrap(),
+                   json!([["Carol", null, true], ["Alice", "cat", false], ["Bob", "dog", false]]));
+        assert_eq!(run(r#"find {name: == "Bob"} return .pets[].kind"#).unwrap(),
+                   json!([["dog", "cat"]]));
+    }
+
+    #[test]
+    fn bind() {
+        assert_eq!(run(r#"find {pets: x::[{kind: == "cat"}]} return x.age"#).unwrap(),
+                   json!([[3], [1]]));
+        assert_eq!(run(r#"find {name: == "Bob", pets: x::[{age: > 2}]} return x"#).unwrap(),
+                   json!([[{"kind": "dog", "age": 5}]]));
+    }
+
+    #[test]
+    fn word_match() {
+        assert_eq!(ids(r#"find {bio: ~= "rust code"}"#), json!(["1"]));
+        assert_eq!(ids(r#"find {bio: ~= "at NIGHT"}"#), json!(["1", "2"]));
+        assert_eq!(ids(r#"find {bio: ~1= "writing rust"}"#), json!(["2"]));
+        assert_eq!(ids(r#"find {bio: ~1= "rust night"}"#), json!([]));
+        assert_eq!(ids(r#"find {bio: ~2= "rust night"}"#), json!(["1", "2"]));
+        assert_eq!(ids(r#"find {bio: !~= "rust code"}"#), json!(["2", "3"]));
+        assert_eq!(ids(r#"find {tags: [~= "ADMIN"]}"#), json!(["1"]));
+    }
+
+    #[test]
+    fn score() {
+        let scores = run(r#"find {bio: ~= "night"} order score() desc return score()"#).unwrap();
+        // The shorter text ranks higher
+        assert_eq!(ids(r#"find {bio: ~= "night"} order score() desc"#), json!(["1", "2"]));
+        assert!(scores[0].as_f64().unwrap() > scores[1].as_f64().unwrap());
+        assert_eq!(ids(r#"find {bio: ~= "night" || bio: ~= "writing"^3} order score() desc"#),
+                   json!(["2", "1"]));
+        // Conditions other than word matches don't score
+        assert_eq!(run(r#"find {age: > 30} return score()"#).unwrap(), json!([0.0]));
+    }
+
+    #[test]
+    fn custom_scorer() {
+        struct WordCount;
+        impl Scorer for WordCount {
+            fn score(&self, _phrase: &Phrase, words: &[String], _field: &FieldStats) -> f64 {
+                words.len() as f64
+            }
+        }
+        let query = parse_query(r#"find {bio: ~= "rust"}^2 || {tags: [~= "dev"]}
+                                   return score()"#).unwrap();
+        assert_eq!(evaluate_with(&query, &documents(), &WordCount).unwrap(),
+                   vec![json!(11.0), json!(13.0)]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(run(r#"find {name: == @name}"#),
+                   Err(EvalError::UnboundParameter("name".to_string())));
+        assert_eq!(run(r#"find {} return [count(), .name]"#),
+                   Err(EvalError::Unsupported("returning values other than aggregates together \
+                                               with aggregate functions")));
+    }
+}
@@ -0,0 +1,339 @@
+//! Formats queries in a consistent multi-line layout
+//!
+//! Queries that fit within the configured line width are kept on a single line. Longer ones get
+//! every clause on its own line and the objects and arrays of the find and the return clause
+//! broken up into one condition per line.
+//!
+//! Comments of queries parsed with `parse_query_with_comments()` are kept, each on a line of its
+//! own before the condition, member or clause that follows it.
+
+use std::cell::RefCell;
+
+use ast::{Comment, Predicate, PredicateKind, Projection, ProjectionKind, Query, Span};
+use printer::Key;
+
+/// Options for the layout of formatted queries
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormatOptions {
+    /// Number of spaces used for one level of indentation
+    pub indent: usize,
+    /// Maximum line width the formatter tries to stay within
+    pub width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> FormatOptions {
+        FormatOptions {
+            indent: 4,
+            width: 80,
+        }
+    }
+}
+
+/// Formats a query according to the given options
+pub fn format_query(query: &Query, options: &FormatOptions) -> String {
+    let flat = query.to_string();
+    if width(&flat) <= options.width && query.comments.is_empty() {
+        return flat;
+    }
+
+    let formatter = Formatter {
+        options,
+        comments: RefCell::new(query.comments.iter().rev().collect()),
+    };
+    let mut lines = Vec::new();
+    lines.extend(formatter.comments_before(query.span.start, 0));
+    match query.find.kind {
+        PredicateKind::All => lines.push("find {}".to_string()),
+        _ => lines.push(format!("find {}", formatter.braced(&query.find, "{", "}", 0, 5))),
+    }
+    if let Some(last) = query.order.last() {
+        lines.extend(formatter.comments_before(last.span.end, 0));
+        let keys: Vec<String> = query.order.iter().map(|key| key.to_string()).collect();
+        lines.push(format!("order {}", keys.join(", ")));
+    }
+    if let Some(ref ret) = query.ret {
+        lines.extend(formatter.comments_before(ret.span.start, 0));
+        lines.push(format!("return {}", formatter.projection(ret, 0, 7)));
+    }
+    if let Some(limit) = query.limit {
+        lines.extend(formatter.comments_before(query.span.end, 0));
+        lines.push(format!("limit {}", limit));
+    }
+    lines.extend(formatter.comments_before(usize::MAX, 0));
+    lines.join("\n")
+}
+
+fn width(string: &str) -> usize {
+    string.chars().count()
+}
+
+/// Splits a left-deep chain of `And`s or `Or`s into its operands
+fn flatten<'a>(predicate: &'a Predicate, items: &mut Vec<&'a Predicate>, or: bool) {
+    match (or, &predicate.kind) {
+        (true, PredicateKind::Or(left, right)) | (false, PredicateKind::And(left, right)) => {
+            flatten(left, items, or);
+            items.push(right);
+        },
+        _ => items.push(predicate),
+    }
+}
+
+struct Formatter<'a> {
+    options: &'a FormatOptions,
+    /// The comments that weren't written yet, the next one is the last
+    comments: RefCell<Vec<&'a Comment>>,
+}
+
+impl<'a> Formatter<'a> {
+    /// Takes the comments that start before the given offset and returns them as indented lines
+    fn comments_before(&self, offset: usize, level: usize) -> Vec<String> {
+        let mut comments = self.comments.borrow_mut();
+        let mut lines = Vec::new();
+        while comments.last().is_some_and(|comment| comment.span.start < offset) {
+            let comment = comments.pop().unwrap();
+            lines.push(format!("{}{}", self.indentation(level), comment.text));
+        }
+        lines
+    }
+
+    /// Returns whether there are comments within the span that weren't written yet, in that case
+    /// the node can't be written on a single line
+    fn has_comments(&self, span: Span) -> bool {
+        self.comments.borrow().iter().any(|comment| comment.span.start < span.end)
+    }
+
+    fn indentation(&self, level: usize) -> String {
+        " ".repeat(self.options.indent * level)
+    }
+
+    fn fits(&self, column: usize, string: &str) -> bool {
+        column + width(string) <= self.options.width
+    }
+
+    /// Formats the conditions of an object or array, `column` is the column the opening bracket
+    /// is at
+    fn braced(&self, predicate: &Predicate, open: &str, close: &str, level: usize,
+              column: usize) -> String {
+        let flat = format!("{}{}{}", open, predicate, close);
+        if self.fits(column, &flat) && !self.has_comments(predicate.span) {
+            return flat;
+        }
+
+        let inner_indentation = self.indentation(level + 1);
+        let mut alternatives = Vec::new();
+        flatten(predicate, &mut alternatives, true);
+        let mut out = format!("{}\n", open);
+        for (ii, alternative) in alternatives.iter().enumerate() {
+            let mut conditions = Vec::new();
+            flatten(alternative, &mut conditions, false);
+            for (jj, condition) in conditions.iter().enumerate() {
+                let prefix = if ii > 0 && jj == 0 { "|| " } else { "" };
+                let column = width(&inner_indentation) + width(prefix);
+                for comment in self.comments_before(condition.span.start, level + 1) {
+                    out.push_str(&comment);
+                    out.push('\n');
+                }
+                out.push_str(&inner_indentation);
+                out.push_str(prefix);
+                out.push_str(&self.key_value(condition, level + 1, column));
+                if jj < conditions.len() - 1 {
+                    out.push(',');
+                }
+                out.push('\n');
+            }
+        }
+        out.push_str(&self.indentation(level));
+        out.push_str(close);
+        out
+    }
+
+    /// Formats a single condition within an object
+    fn key_value(&self, predicate: &Predicate, level: usize, column: usize) -> String {
+        match predicate.kind {
+            PredicateKind::And(..) | PredicateKind::Or(..) => {
+                self.braced(predicate, "(", ")", level, column)
+            },
+            PredicateKind::Object(ref key, ref value) => {
+                let flat = predicate.to_string();
+                if self.fits(column, &flat) && !self.has_comments(predicate.span) {
+                    return flat;
+                }
+                let key = format!("{}: ", Key(&key.name));
+                let column = column + width(&key);
+                format!("{}{}", key, self.value(value, level, column))
+            },
+            _ => predicate.to_string(),
+        }
+    }
+
+    /// Formats the value of a key, which might be negated, boosted or bound to a variable
+    fn value(&self, predicate: &Predicate, level: usize, column: usize) -> String {
+        match predicate.kind {
+            PredicateKind::Not(ref inner) => format!("!{}", self.boosted(inner, level, column + 1)),
+            _ => self.boosted(predicate, level, column),
+        }
+    }
+
+    fn boosted(&self, predicate: &Predicate, level: usize, column: usize) -> String {
+        match predicate.kind {
+            PredicateKind::Boost(boost, ref inner) => {
+                format!("{}^{}", self.plain(inner, level, column), boost)
+            },
+            _ => self.plain(predicate, level, column),
+        }
+    }
+
+    fn plain(&self, predicate: &Predicate, level: usize, column: usize) -> String {
+        match predicate.kind {
+            PredicateKind::Array(ref inner) => match inner.kind {
+                PredicateKind::Array(_) => format!("[{}]", self.plain(inner, level, column + 1)),
+                _ => self.braced(inner, "[", "]", level, column),
+            },
+            PredicateKind::Bind(ref name, ref inner) => {
+                let prefix = format!("{}::", name);
+                let column = column + width(&prefix);
+                format!("{}{}", prefix, self.value(inner, level, column))
+            },
+            _ => self.braced(predicate, "{", "}", level, column),
+        }
+    }
+
+    /// Formats the return clause, `column` is the column it starts at
+    fn projection(&self, projection: &Projection, level: usize, column: usize) -> String {
+        let flat = projection.to_string();
+        if self.fits(column, &flat) && !self.has_comments(projection.span) {
+            return flat;
+        }
+
+        let inner_indentation = self.indentation(level + 1);
+        let column = width(&inner_indentation);
+        match projection.kind {
+            ProjectionKind::Object(ref members) if !members.is_empty() => {
+                let mut out = "{\n".to_string();
+                for (ii, (key, value)) in members.iter().enumerate() {
+                    for comment in self.comments_before(key.span.start, level + 1) {
+                        out.push_str(&comment);
+                        out.push('\n');
+                    }
+                    let key = format!("{}: ", Key(&key.name));
+                    out.push_str(&inner_indentation);
+                    out.push_str(&key);
+                    out.push_str(&self.projection(value, level + 1, column + width(&key)));
+                    if ii < members.len() - 1 {
+                        out.push(',');
+                    }
+                    out.push('\n');
+                }
+                out.push_str(&self.indentation(level));
+                out.push('}');
+                out
+            },
+            ProjectionKind::Array(ref values) if !values.is_empty() => {
+                let mut out = "[\n".to_string();
+                for (ii, value) in values.iter().enumerate() {
+                    for comment in self.comments_before(value.span.start, level + 1) {
+                        out.push_str(&comment);
+                        out.push('\n');
+                    }
+                    out.push_str(&inner_indentation);
+                    out.push_str(&self.projection(value, level + 1, column));
+                    if ii < values.len() - 1 {
+                        out.push(',');
+                    }
+                    out.push('\n');
+                }
+                out.push_str(&self.indentation(level));
+                out.push(']');
+                out
+            },
+            _ => flat,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use {parse_query, parse_query_with_comments};
+
+    fn format(query: &str, width: usize) -> String {
+        let options = FormatOptions {
+            indent: 2,
+            width,
+        };
+        let formatted = format_query(&parse_query(query).unwrap(), &options);
+        // Formatting must never change the meaning of a query
+        assert_eq!(parse_query(&formatted).unwrap(), parse_query(query).unwrap());
+        formatted
+    }
+
+    #[test]
+    fn short_queries_stay_on_one_line() {
+        assert_eq!(format(r#"find {a:==1,"b":>2} return .a"#, 80),
+                   r#"find {a: == 1, b: > 2} return .a"#);
+    }
+
+    #[test]
+    fn long_find() {
+        assert_eq!(format(r#"find {name: == "Noise", nested: {deeply: {a: == 1, b: == 2}},
+                                   tags: x::[== "search" || == "json"]^2, c: ~= "text"}
+                             || {d: == null}"#, 40),
+                   "find {\n\
+                    \x20 name: == \"Noise\",\n\
+                    \x20 nested: {deeply: {a: == 1, b: == 2}},\n\
+                    \x20 tags: x::[== \"search\" || == \"json\"]^2,\n\
+                    \x20 c: ~= \"text\"\n\
+                    \x20 || d: == null\n\
+                    }");
+        assert_eq!(format(r#"find {nested: {deeply: {a: == 1, b: == 2}}}"#, 20),
+                   "find {\n\
+                    \x20 nested: {\n\
+                    \x20   deeply: {\n\
+                    \x20     a: == 1,\n\
+                    \x20     b: == 2\n\
+                    \x20   }\n\
+                    \x20 }\n\
+                    }");
+    }
+
+    #[test]
+    fn long_return() {
+        assert_eq!(format(r#"find {} order .a desc return {a: .a, b: [.b, .c], c: {d: .d}}
+                             limit 5"#, 20),
+                   "find {}\n\
+                    order .a desc\n\
+                    return {\n\
+                    \x20 a: .a,\n\
+                    \x20 b: [.b, .c],\n\
+                    \x20 c: {d: .d}\n\
+                    }\n\
+                    limit 5");
+    }
+
+    #[test]
+    fn comments() {
+        let query = "# Active users\nfind {/* the name */ name: == \"Noise\", # tags\n
+                     tags: [== \"json\"]} return [.name, # and the tags\n.tags] limit 5 # at most";
+        let formatted = format_query(&parse_query_with_comments(query).unwrap(),
+                                     &FormatOptions::default());
+        assert_eq!(formatted,
+                   "# Active users\n\
+                    find {\n\
+                    \x20   /* the name */\n\
+                    \x20   name: == \"Noise\",\n\
+                    \x20   # tags\n\
+                    \x20   tags: [== \"json\"]\n\
+                    }\n\
+                    return [\n\
+                    \x20   .name,\n\
+                    \x20   # and the tags\n\
+                    \x20   .tags\n\
+                    ]\n\
+                    limit 5\n\
+                    # at most");
+        assert_eq!(parse_query_with_comments(&formatted).unwrap().comments.len(), 5);
+        assert_eq!(parse_query(&formatted).unwrap(), parse_query(query).unwrap());
+    }
+}
@@ -0,0 +1,407 @@
+//! Splits a query into tokens for the parser
+//!
+//! Whitespace and comments are skipped, keywords are told apart from field names, strings are
+//! unescaped and numbers are classified here, so that the grammar only deals with tokens.
+
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use ast::{Comment, Span};
+use error::{ErrorCode, GrammarError};
+use escape::unescape;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tok<'input> {
+    // Keywords
+    Array,
+    ArrayFlat,
+    Asc,
+    Avg,
+    Concat,
+    Count,
+    Default,
+    Desc,
+    False,
+    Find,
+    Group,
+    Limit,
+    Max,
+    MaxArray,
+    Min,
+    MinArray,
+    Null,
+    Order,
+    Return,
+    Score,
+    Sep,
+    Sum,
+    True,
+
+    /// A name that isn't a keyword
+    Field(&'input str),
+    /// A field name directly preceded by a dot, e.g. `.name`. The dot isn't part of it.
+    DotField(&'input str),
+    /// A parameter like `@name`, without the `@`
+    Parameter(&'input str),
+    /// The value of a string literal, with all escapes resolved
+    String(String),
+    /// A number that consists of digits only
+    Integer(&'input str),
+    /// Any other number, i.e. one with a sign, a fraction or an exponent
+    Number(&'input str),
+
+    // Punctuation and operators
+    AmpAmp,
+    Bang,
+    BangEqual,
+    BangTilde,
+    BangTildeEqual,
+    Caret,
+    Colon,
+    ColonColon,
+    Comma,
+    Dot,
+    Equal,
+    EqualEqual,
+    Greater,
+    GreaterEqual,
+    LeftBrace,
+    LeftBracket,
+    LeftParen,
+    Less,
+    LessEqual,
+    PipePipe,
+    RightBrace,
+    RightBracket,
+    RightParen,
+    Tilde,
+    TildeEqual,
+}
+
+const KEYWORDS: &[(&str, Tok<'static>)] = &[
+    ("array", Tok::Array),
+    ("array_flat", Tok::ArrayFlat),
+    ("asc", Tok::Asc),
+    ("avg", Tok::Avg),
+    ("concat", Tok::Concat),
+    ("count", Tok::Count),
+    ("default", Tok::Default),
+    ("desc", Tok::Desc),
+    ("false", Tok::False),
+    ("find", Tok::Find),
+    ("group", Tok::Group),
+    ("limit", Tok::Limit),
+    ("max", Tok::Max),
+    ("max_array", Tok::MaxArray),
+    ("min", Tok::Min),
+    ("min_array", Tok::MinArray),
+    ("null", Tok::Null),
+    ("order", Tok::Order),
+    ("return", Tok::Return),
+    ("score", Tok::Score),
+    ("sep", Tok::Sep),
+    ("sum", Tok::Sum),
+    ("true", Tok::True),
+];
+
+pub type Spanned<'input> = (usize, Tok<'input>, usize);
+
+/// An iterator over the tokens of a query and their byte offsets. The comments it skipped are
+/// collected.
+pub struct Lexer<'input> {
+    input: &'input str,
+    chars: Peekable<CharIndices<'input>>,
+    comments: Vec<Comment>,
+}
+
+impl<'input> Lexer<'input> {
+    pub fn new(input: &'input str) -> Lexer<'input> {
+        Lexer {
+            input,
+            chars: input.char_indices().peekable(),
+            comments: Vec::new(),
+        }
+    }
+
+    /// Returns the comments of the tokens that were read so far
+    pub fn into_comments(self) -> Vec<Comment> {
+        self.comments
+    }
+
+    /// The byte offset of the next character
+    fn offset(&mut self) -> usize {
+        self.chars.peek().map_or(self.input.len(), |&(pos, _)| pos)
+    }
+
+    fn next_is(&mut self, expected: char) -> bool {
+        self.chars.peek().is_some_and(|&(_, cc)| cc == expected)
+    }
+
+    /// Consumes the next character if it's the expected one
+    fn eat(&mut self, expected: char) -> bool {
+        let matches = self.next_is(expected);
+        if matches {
+            self.chars.next();
+        }
+        matches
+    }
+
+    fn eat_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> usize {
+        while self.chars.peek().is_some_and(|&(_, cc)| predicate(cc)) {
+            self.chars.next();
+        }
+        self.offset()
+    }
+
+    /// Skips whitespace, `# ...` comments up to the end of the line and `/* ... */` comments
+    fn skip_trivia(&mut self) -> Result<(), GrammarError> {
+        loop {
+            let start = self.eat_while(char::is_whitespace);
+            let end = if self.next_is('#') {
+                self.eat_while(|cc| cc != '\n')
+            } else if self.next_is('/') && self.input[start..].starts_with("/*") {
+                match self.input[start + 2..].find("*/") {
+                    Some(pos) => {
+                        let end = start + 2 + pos + 2;
+                        while self.offset() < end {
+                            self.chars.next();
+                        }
+                        end
+                    },
+                    None => {
+                        // The rest of the input is part of the comment
+                        while self.chars.next().is_some() {}
+                        return Err(GrammarError::new(ErrorCode::InvalidToken,
+                                                     Span::new(start, self.input.len()),
+                                                     "the comment is never closed".to_string()))
+                    },
+                }
+            } else {
+                return Ok(());
+            };
+            let text = self.input[start..end].trim_end();
+            self.comments.push(Comment {
+                text: text.to_string(),
+                span: Span::new(start, start + text.len()),
+            });
+        }
+    }
+
+    /// Consumes the rest of a name and returns it, `start` is the offset of its first character
+    fn name(&mut self, start: usize) -> &'input str {
+        let end = self.eat_while(is_name_continue);
+        &self.input[start..end]
+    }
+
+    fn string(&mut self, start: usize) -> Result<Spanned<'input>, GrammarError> {
+        loop {
+            match self.chars.next() {
+                Some((_, '\\')) => {
+                    self.chars.next();
+                },
+                Some((pos, '"')) => {
+                    let value = unescape(&self.input[start + 1..pos], start + 1)?;
+                    return Ok((start, Tok::String(value), pos + 1));
+                },
+                Some(_) => {},
+                None => {
+                    return Err(GrammarError::new(ErrorCode::InvalidToken,
+                                                 Span::new(start, self.input.len()),
+                                                 "the string is never closed".to_string()))
+                },
+            }
+        }
+    }
+
+    /// Numbers are JSON numbers, except that integers may start with zeros
+    fn number(&mut self, start: usize) -> Spanned<'input> {
+        let negative = self.input[start..].starts_with('-');
+        let mut end = self.eat_while(|cc| cc.is_ascii_digit());
+        let mut integer = !negative;
+        if self.next_is('.') && self.digit_after(1) {
+            self.chars.next();
+            end = self.eat_while(|cc| cc.is_ascii_digit());
+            integer = false;
+        }
+        if (self.next_is('e') || self.next_is('E')) &&
+            (self.digit_after(1) || (self.sign_after(1) && self.digit_after(2))) {
+            self.chars.next();
+            if self.next_is('+') || self.next_is('-') {
+                self.chars.next();
+            }
+            end = self.eat_while(|cc| cc.is_ascii_digit());
+            integer = false;
+        }
+        let text = &self.input[start..end];
+        if integer {
+            (start, Tok::Integer(text), end)
+        } else {
+            (start, Tok::Number(text), end)
+        }
+    }
+
+    /// Returns whether the character `ahead` characters after the next one is a digit
+    fn digit_after(&self, ahead: usize) -> bool {
+        self.chars.clone().nth(ahead).is_some_and(|(_, cc)| cc.is_ascii_digit())
+    }
+
+    fn sign_after(&self, ahead: usize) -> bool {
+        self.chars.clone().nth(ahead).is_some_and(|(_, cc)| cc == '+' || cc == '-')
+    }
+
+    fn invalid(&self, start: usize, cc: char) -> GrammarError {
+        GrammarError::new(ErrorCode::InvalidToken,
+                          Span::new(start, start + cc.len_utf8()),
+                          format!("invalid token `{}`", cc))
+    }
+}
+
+impl<'input> Iterator for Lexer<'input> {
+    type Item = Result<Spanned<'input>, GrammarError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(error) = self.skip_trivia() {
+            return Some(Err(error));
+        }
+        let (start, cc) = self.chars.next()?;
+        let single = |tok| Some(Ok((start, tok, start + cc.len_utf8())));
+        let double = |tok| Some(Ok((start, tok, start + 2)));
+        match cc {
+            '{' => single(Tok::LeftBrace),
+            '}' => single(Tok::RightBrace),
+            '[' => single(Tok::LeftBracket),
+            ']' => single(Tok::RightBracket),
+            '(' => single(Tok::LeftParen),
+            ')' => single(Tok::RightParen),
+            ',' => single(Tok::Comma),
+            '^' => single(Tok::Caret),
+            ':' if self.eat(':') => double(Tok::ColonColon),
+            ':' => single(Tok::Colon),
+            '=' if self.eat('=') => double(Tok::EqualEqual),
+            '=' => single(Tok::Equal),
+            '>' if self.eat('=') => double(Tok::GreaterEqual),
+            '>' => single(Tok::Greater),
+            '<' if self.eat('=') => double(Tok::LessEqual),
+            '<' => single(Tok::Less),
+            '~' if self.eat('=') => double(Tok::TildeEqual),
+            '~' => single(Tok::Tilde),
+            '!' if self.eat('=') => double(Tok::BangEqual),
+            '!' if self.eat('~') => {
+                if self.eat('=') {
+                    Some(Ok((start, Tok::BangTildeEqual, start + 3)))
+                } else {
+                    double(Tok::BangTilde)
+                }
+            },
+            '!' => single(Tok::Bang),
+            '&' if self.eat('&') => double(Tok::AmpAmp),
+            '|' if self.eat('|') => double(Tok::PipePipe),
+            '"' => Some(self.string(start)),
+            '.' if self.chars.peek().is_some_and(|&(_, next)| is_name_start(next)) => {
+                let name = self.name(start + 1);
+                Some(Ok((start, Tok::DotField(name), start + 1 + name.len())))
+            },
+            '.' => single(Tok::Dot),
+            '@' if self.chars.peek().is_some_and(|&(_, next)| is_name_start(next)) => {
+                let name = self.name(start + 1);
+                Some(Ok((start, Tok::Parameter(name), start + 1 + name.len())))
+            },
+            '-' if self.chars.peek().is_some_and(|&(_, next)| next.is_ascii_digit()) => {
+                Some(Ok(self.number(start)))
+            },
+            cc if cc.is_ascii_digit() => Some(Ok(self.number(start))),
+            cc if is_name_start(cc) => {
+                let name = self.name(start);
+                let tok = KEYWORDS.iter()
+                    .find(|&&(keyword, _)| keyword == name)
+                    .map_or(Tok::Field(name), |(_, keyword)| keyword.clone());
+                Some(Ok((start, tok, start + name.len())))
+            },
+            cc => Some(Err(self.invalid(start, cc))),
+        }
+    }
+}
+
+fn is_name_start(cc: char) -> bool {
+    cc.is_alphabetic() || cc == '_' || cc == '$'
+}
+
+fn is_name_continue(cc: char) -> bool {
+    cc.is_alphabetic() || cc.is_numeric() || cc == '_' || cc == '$'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<Tok<'_>> {
+        Lexer::new(input).map(|token| token.unwrap().1).collect()
+    }
+
+    #[test]
+    fn keywords_and_names() {
+        assert_eq!(tokens("find finder $id _ max_array\tmaximal"),
+                   vec![Tok::Find, Tok::Field("finder"), Tok::Field("$id"), Tok::Field("_"),
+                        Tok::MaxArray, Tok::Field("maximal")]);
+        assert_eq!(tokens(".a.été[0] . @name"),
+                   vec![Tok::DotField("a"), Tok::DotField("été"), Tok::LeftBracket,
+                        Tok::Integer("0"), Tok::RightBracket, Tok::Dot, Tok::Parameter("name")]);
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(tokens("{}:::!~=!~2=!=! ==~=~&&||>=<="),
+                   vec![Tok::LeftBrace, Tok::RightBrace, Tok::ColonColon, Tok::Colon,
+                        Tok::BangTildeEqual, Tok::BangTilde, Tok::Integer("2"), Tok::Equal,
+                        Tok::BangEqual, Tok::Bang, Tok::EqualEqual, Tok::TildeEqual, Tok::Tilde,
+                        Tok::AmpAmp, Tok::PipePipe, Tok::GreaterEqual, Tok::LessEqual]);
+    }
+
+    #[test]
+    fn numbers_and_strings() {
+        assert_eq!(tokens(r#"12 007 -3 1.5 2e10 -0.5E-3 1.x "a\"b""#),
+                   vec![Tok::Integer("12"), Tok::Integer("007"), Tok::Number("-3"),
+                        Tok::Number("1.5"), Tok::Number("2e10"), Tok::Number("-0.5E-3"),
+                        Tok::Integer("1"), Tok::DotField("x"),
+                        Tok::String("a\"b".to_string())]);
+        let spans: Vec<(usize, usize)> = Lexer::new(r#" "é" 1e5 "#)
+            .map(|token| token.map(|(start, _, end)| (start, end)).unwrap())
+            .collect();
+        assert_eq!(spans, vec![(1, 5), (6, 9)]);
+    }
+
+    #[test]
+    fn comments() {
+        let mut lexer = Lexer::new("find # the find clause\n{/* a */a/**/: # \n== 1} #");
+        assert_eq!(lexer.by_ref().map(|token| token.unwrap().1).collect::<Vec<_>>(),
+                   vec![Tok::Find, Tok::LeftBrace, Tok::Field("a"), Tok::Colon, Tok::EqualEqual,
+                        Tok::Integer("1"), Tok::RightBrace]);
+        let comments: Vec<(String, Span)> = lexer.into_comments()
+            .into_iter()
+            .map(|comment| (comment.text, comment.span))
+            .collect();
+        assert_eq!(comments, vec![("# the find clause".to_string(), Span::new(5, 22)),
+                                  ("/* a */".to_string(), Span::new(24, 31)),
+                                  ("/**/".to_string(), Span::new(32, 36)),
+                                  ("#".to_string(), Span::new(38, 39)),
+                                  ("#".to_string(), Span::new(47, 48))]);
+
+        let error = Lexer::new("find /* open").nth(1).unwrap().unwrap_err();
+        assert_eq!((error.code, error.span), (ErrorCode::InvalidToken, Span::new(5, 12)));
+        let tokens: Vec<_> = Lexer::new("/* x").collect();
+        assert!(matches!(tokens[..], [Err(_)]));
+        assert!(Lexer::new("a / b").nth(1).unwrap().is_err());
+    }
+
+    #[test]
+    fn errors() {
+        let error = Lexer::new("a % b").nth(1).unwrap().unwrap_err();
+        assert_eq!((error.code, error.span), (ErrorCode::InvalidToken, Span::new(2, 3)));
+        let error = Lexer::new(r#"a "open"#).nth(1).unwrap().unwrap_err();
+        assert_eq!((error.code, error.span), (ErrorCode::InvalidToken, Span::new(2, 7)));
+        let error = Lexer::new(r#""\q""#).next().unwrap().unwrap_err();
+        assert_eq!((error.code, error.span), (ErrorCode::InvalidEscape, Span::new(1, 3)));
+        assert!(Lexer::new("a & b").nth(1).unwrap().is_err());
+        assert!(Lexer::new("@ -").next().unwrap().is_err());
+    }
+}
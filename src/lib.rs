@@ -0,0 +1,512 @@
+extern crate lalrpop_util;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg_attr(test, macro_use)]
+extern crate serde_json;
+extern crate unicode_segmentation;
+
+pub mod aggregate;
+pub mod ast;
+pub mod containment;
+pub mod error;
+mod escape;
+pub mod lexer;
+pub mod eval;
+pub mod format;
+#[allow(clippy::all, warnings)]
+mod noise;
+pub mod optimize;
+pub mod parameters;
+pub mod plan;
+mod printer;
+pub mod range;
+#[cfg(feature = "serde")]
+pub mod schema;
+pub mod score;
+pub mod split;
+pub mod text;
+pub mod validate;
+pub mod visit;
+
+pub use ast::Query;
+pub use error::NoiseError;
+use lexer::Lexer;
+pub use parameters::bind_parameters;
+pub use validate::validate;
+
+/// Parses a Noise query
+pub fn parse_query(query: &str) -> Result<Query, NoiseError> {
+    noise::parse_Noise(Lexer::new(query)).map_err(|err| NoiseError::from_parse_error(query, err))
+}
+
+/// Parses a Noise query like `parse_query()`, but keeps its comments in `Query::comments`
+pub fn parse_query_with_comments(query: &str) -> Result<Query, NoiseError> {
+    let mut lexer = Lexer::new(query);
+    let mut parsed = noise::parse_Noise(&mut lexer)
+        .map_err(|err| NoiseError::from_parse_error(query, err))?;
+    parsed.comments = lexer.into_comments();
+    Ok(parsed)
+}
+
+/// Parses the query and makes sure that printing and parsing it again leads to the same result
+#[cfg(test)]
+fn parse_roundtrip(query: &str) -> Result<Query, NoiseError> {
+    let parsed = parse_query(query);
+    if let Ok(ref parsed) = parsed {
+        let printed = parsed.to_string();
+        assert_eq!(parse_query(&printed).as_ref(), Ok(parsed), "printed as: {}", printed);
+    }
+    parsed
+}
+
+#[test]
+fn noise() {
+    // Simple
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {}"#)),
+               r#"Ok(Query { find: All, order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip("find { }\nreturn {\n}")),
+               r#"Ok(Query { find: All, order: [], ret: Some(Object([])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world"}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), String("world")), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {hello: == "world"}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), String("world")), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hell \"escaped\"": == "world"}"#)),
+               r#"Ok(Query { find: Equal(Some("hell \"escaped\""), String("world")), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"caf\u00e9": == "\ud83d\ude00\n"}"#)),
+               r#"Ok(Query { find: Equal(Some("café"), String("😀\n")), order: [], ret: None, limit: None })"#);
+
+    // Nested
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": {"nested": == "world"}}"#)),
+               r#"Ok(Query { find: Object("hello", Equal(Some("nested"), String("world"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {hello: {nested: == "world"}}"#)),
+               r#"Ok(Query { find: Object("hello", Equal(Some("nested"), String("world"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": {"deeply": {"nested": == "world"}}}"#)),
+               r#"Ok(Query { find: Object("hello", Object("deeply", Equal(Some("nested"), String("world")))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world", "another": == "one"}"#)),
+               r#"Ok(Query { find: And(Equal(Some("hello"), String("world")), Equal(Some("another"), String("one"))), order: [], ret: None, limit: None })"#);
+
+    // Boolean
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world", "another": == "one", "third": == "thing"}"#)),
+               r#"Ok(Query { find: And(And(Equal(Some("hello"), String("world")), Equal(Some("another"), String("one"))), Equal(Some("third"), String("thing"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world", "another": == "one" || "third": == "thing"}"#)),
+               r#"Ok(Query { find: Or(And(Equal(Some("hello"), String("world")), Equal(Some("another"), String("one"))), Equal(Some("third"), String("thing"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world" || "another": == "one", "third": == "thing"}"#)),
+               r#"Ok(Query { find: Or(Equal(Some("hello"), String("world")), And(Equal(Some("another"), String("one")), Equal(Some("third"), String("thing")))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world" || "another": == "one" || "third": == "thing"}"#)),
+               r#"Ok(Query { find: Or(Or(Equal(Some("hello"), String("world")), Equal(Some("another"), String("one"))), Equal(Some("third"), String("thing"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world" && "another": == "one" || "third": == "thing"}"#)),
+               r#"Ok(Query { find: Or(And(Equal(Some("hello"), String("world")), Equal(Some("another"), String("one"))), Equal(Some("third"), String("thing"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world"} && {"another": == "one"}"#)),
+               r#"Ok(Query { find: And(Equal(Some("hello"), String("world")), Equal(Some("another"), String("one"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world"} || {"another": == "one"} && {"third": == "thing"}"#)),
+               r#"Ok(Query { find: Or(Equal(Some("hello"), String("world")), And(Equal(Some("another"), String("one")), Equal(Some("third"), String("thing")))), order: [], ret: None, limit: None })"#);
+
+    // Parenthesis
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world", ("another": == "one" || "third": == "thing")}"#)),
+               r#"Ok(Query { find: And(Equal(Some("hello"), String("world")), Or(Equal(Some("another"), String("one")), Equal(Some("third"), String("thing")))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world", ("another": == "one", "third": == "thing")}"#)),
+               r#"Ok(Query { find: And(Equal(Some("hello"), String("world")), And(Equal(Some("another"), String("one")), Equal(Some("third"), String("thing")))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find ({"hello": == "world"})"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), String("world")), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find ({"hello": == "world"} || {"another": == "one"}) && {"third": == "thing"}"#)),
+               r#"Ok(Query { find: And(Or(Equal(Some("hello"), String("world")), Equal(Some("another"), String("one"))), Equal(Some("third"), String("thing"))), order: [], ret: None, limit: None })"#);
+
+    // Arrays
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": [{"array": == "world"}]}"#)),
+               r#"Ok(Query { find: Object("hello", Array(Equal(Some("array"), String("world")))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": [{"array": == "world", "another": == "one"}]}"#)),
+               r#"Ok(Query { find: Object("hello", Array(And(Equal(Some("array"), String("world")), Equal(Some("another"), String("one"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": [{"array": == "world"}, {"another": == "one"}]}"#)),
+               r#"Ok(Query { find: Object("hello", Array(And(Equal(Some("array"), String("world")), Equal(Some("another"), String("one"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": [== "world"]}"#)),
+               r#"Ok(Query { find: Object("hello", Array(Equal(None, String("world")))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": [[== "world"]]}"#)),
+               r#"Ok(Query { find: Object("hello", Array(Array(Equal(None, String("world"))))), order: [], ret: None, limit: None })"#);
+
+    // Boost
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world"^2}"#)),
+               r#"Ok(Query { find: Boost(2, Equal(Some("hello"), String("world"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world"^2, "another": == "one"}"#)),
+               r#"Ok(Query { find: And(Boost(2, Equal(Some("hello"), String("world"))), Equal(Some("another"), String("one"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {("hello": == "world")^2}"#)),
+               r#"Ok(Query { find: Boost(2, Equal(Some("hello"), String("world"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world"}^2"#)),
+               r#"Ok(Query { find: Boost(2, Equal(Some("hello"), String("world"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": {"nested": == "world"}^2}"#)),
+               r#"Ok(Query { find: Object("hello", Boost(2, Equal(Some("nested"), String("world")))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": [{"array": == "world"}]^2}"#)),
+               r#"Ok(Query { find: Object("hello", Boost(2, Array(Equal(Some("array"), String("world"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": [{"array": == "world"}^2]}"#)),
+               r#"Ok(Query { find: Object("hello", Array(Boost(2, Equal(Some("array"), String("world"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": [{"array": == "world", "another": == "one"}]^2}"#)),
+               r#"Ok(Query { find: Object("hello", Boost(2, Array(And(Equal(Some("array"), String("world")), Equal(Some("another"), String("one")))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": [{"array": == "world"^2, "another": == "one"}]}"#)),
+               r#"Ok(Query { find: Object("hello", Array(And(Boost(2, Equal(Some("array"), String("world"))), Equal(Some("another"), String("one"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": [== "world"]^2}"#)),
+               r#"Ok(Query { find: Object("hello", Boost(2, Array(Equal(None, String("world"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": [== "world"^2]}"#)),
+               r#"Ok(Query { find: Object("hello", Array(Boost(2, Equal(None, String("world"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": [== "world"^2 || == "another"]}"#)),
+               r#"Ok(Query { find: Object("hello", Array(Or(Boost(2, Equal(None, String("world"))), Equal(None, String("another"))))), order: [], ret: None, limit: None })"#);
+
+    // Bind variables
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": xyz::[== "world"]}"#)),
+               r#"Ok(Query { find: Object("hello", Bind("xyz", Array(Equal(None, String("world"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": array_flat::[== "world"]}"#)),
+               r#"Ok(Query { find: Object("hello", Bind("array_flat", Array(Equal(None, String("world"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": find::[== "world"]}"#)),
+               r#"Ok(Query { find: Object("hello", Bind("find", Array(Equal(None, String("world"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": order::[== "world"]}"#)),
+               r#"Ok(Query { find: Object("hello", Bind("order", Array(Equal(None, String("world"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": return::[== "world"]}"#)),
+               r#"Ok(Query { find: Object("hello", Bind("return", Array(Equal(None, String("world"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": limit::[== "world"]}"#)),
+               r#"Ok(Query { find: Object("hello", Bind("limit", Array(Equal(None, String("world"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": asc::[== "world"]}"#)),
+               r#"Ok(Query { find: Object("hello", Bind("asc", Array(Equal(None, String("world"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": desc::[== "world"]}"#)),
+               r#"Ok(Query { find: Object("hello", Bind("desc", Array(Equal(None, String("world"))))), order: [], ret: None, limit: None })"#);
+
+    // Different types of values
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == null}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), Null), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == false}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), Bool(false)), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == true}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), Bool(true)), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == 300}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), Number(300)), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == 3.14}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), Number(3.14)), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == 9007199254740993}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), Number(9007199254740993)), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == -9223372036854775808}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), Number(-9223372036854775808)), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == -0}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), Number(0)), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == 1.000000000000000000001}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), Number(1.000000000000000000001)), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == 2e300}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), Number(2e300)), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "null"}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), String("null")), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "false"}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), String("false")), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "true"}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), String("true")), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "300"}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), String("300")), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "3.14"}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), String("3.14")), order: [], ret: None, limit: None })"#);
+
+    // Operators
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": ~= "world"}"#)),
+               r#"Ok(Query { find: WordMatch(Some("hello"), None, String("world")), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": ~34= "world"}"#)),
+               r#"Ok(Query { find: WordMatch(Some("hello"), Some(34), String("world")), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": > 10}"#)),
+               r#"Ok(Query { find: Greater(Some("hello"), Number(10)), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": >= 10}"#)),
+               r#"Ok(Query { find: GreaterEqual(Some("hello"), Number(10)), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": < 10}"#)),
+               r#"Ok(Query { find: Less(Some("hello"), Number(10)), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": <= 10}"#)),
+               r#"Ok(Query { find: LessEqual(Some("hello"), Number(10)), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": && [10, 20, 30, 40]}"#)),
+               r#"Ok(Query { find: Intersect(Some("hello"), Bbox { west: 10.0, south: 20.0, east: 30.0, north: 40.0 }), order: [], ret: None, limit: None })"#);
+
+    // Not
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find !{"hello": == "world"}"#)),
+               r#"Ok(Query { find: Not(Equal(Some("hello"), String("world"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": != "world"}"#)),
+               r#"Ok(Query { find: Not(Equal(Some("hello"), String("world"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": !~= "world"}"#)),
+               r#"Ok(Query { find: Not(WordMatch(Some("hello"), None, String("world"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": !~123= "world"}"#)),
+               r#"Ok(Query { find: Not(WordMatch(Some("hello"), Some(123), String("world"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find !({"hello": == "world"})"#)),
+               r#"Ok(Query { find: Not(Equal(Some("hello"), String("world"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": !{"nested": == "world"}}"#)),
+               r#"Ok(Query { find: Object("hello", Not(Equal(Some("nested"), String("world")))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": !({"nested": == "world"})}"#)),
+               r#"Ok(Query { find: Object("hello", Not(Equal(Some("nested"), String("world")))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": {"nested": != "world"}}"#)),
+               r#"Ok(Query { find: Object("hello", Not(Equal(Some("nested"), String("world")))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": [!{"array": == "world"}]}"#)),
+               r#"Ok(Query { find: Object("hello", Array(Not(Equal(Some("array"), String("world"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": ![== "world"]}"#)),
+               r#"Ok(Query { find: Object("hello", Not(Array(Equal(None, String("world"))))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == "world", !("another": == "one")}"#)),
+               r#"Ok(Query { find: And(Equal(Some("hello"), String("world")), Not(Equal(Some("another"), String("one")))), order: [], ret: None, limit: None })"#);
+
+    // Order
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order asc"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: None, order: Asc }], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order desc"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: None, order: Desc }], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order .hello"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: Some(Path(Path { parts: [Field("hello")], default: None })), order: None }], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order .hello default=["abc", true]"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: Some(Path(Path { parts: [Field("hello")], default: Some(Array([String("abc"), Bool(true)])) })), order: None }], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order .hello asc"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: Some(Path(Path { parts: [Field("hello")], default: None })), order: Asc }], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order .hello desc"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: Some(Path(Path { parts: [Field("hello")], default: None })), order: Desc }], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order .hello default=null desc"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: Some(Path(Path { parts: [Field("hello")], default: Some(Null) })), order: Desc }], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order .hello, .world"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: Some(Path(Path { parts: [Field("hello")], default: None })), order: None }, OrderKey { by: Some(Path(Path { parts: [Field("world")], default: None })), order: None }], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order .hello asc, .world desc"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: Some(Path(Path { parts: [Field("hello")], default: None })), order: Asc }, OrderKey { by: Some(Path(Path { parts: [Field("world")], default: None })), order: Desc }], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order .hello default=1 asc, .world desc"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: Some(Path(Path { parts: [Field("hello")], default: Some(Number(1)) })), order: Asc }, OrderKey { by: Some(Path(Path { parts: [Field("world")], default: None })), order: Desc }], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order .hello asc, .world default={"some": "default"} desc"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: Some(Path(Path { parts: [Field("hello")], default: None })), order: Asc }, OrderKey { by: Some(Path(Path { parts: [Field("world")], default: Some(Object([("some", String("default"))])) })), order: Desc }], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order score()"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: Some(Score), order: None }], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order score() asc"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: Some(Score), order: Asc }], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order score() desc"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: Some(Score), order: Desc }], ret: None, limit: None })"#);
+
+    // Return
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return ."#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(All), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: None })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello[]"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello"), Array], default: None })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello[0].nested"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello"), Index(0), Field("nested")], default: None })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return {"nested": .hello}"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Object([("nested", Path(Path { parts: [Field("hello")], default: None }))])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return {"nested": {"deeper": .hello}}"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Object([("nested", Object([("deeper", Path(Path { parts: [Field("hello")], default: None }))]))])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return {"nested": {"deeper": .hello, "one": .world}}"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Object([("nested", Object([("deeper", Path(Path { parts: [Field("hello")], default: None })), ("one", Path(Path { parts: [Field("world")], default: None }))]))])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return [.hello]"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Array([Path(Path { parts: [Field("hello")], default: None })])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return [.hello, .another[5], .third.one]"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Array([Path(Path { parts: [Field("hello")], default: None }), Path(Path { parts: [Field("another"), Index(5)], default: None }), Path(Path { parts: [Field("third"), Field("one")], default: None })])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return [.hello, {"nested": .one}]"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Array([Path(Path { parts: [Field("hello")], default: None }), Object([("nested", Path(Path { parts: [Field("one")], default: None }))])])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return {"hello": [{"array": .nested}]}"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Object([("hello", Array([Object([("array", Path(Path { parts: [Field("nested")], default: None }))])]))])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return {"nested": [.array]}"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Object([("nested", Array([Path(Path { parts: [Field("array")], default: None })]))])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return ._id"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("_id")], default: None })), limit: None })"#);
+
+    // Return bind variables
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return hello"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Bind("hello", None)), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return hello.nested[0]"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Bind("hello", Some(Path { parts: [Field("nested"), Index(0)], default: None }))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return array"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Bind("array", None)), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return find"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Bind("find", None)), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return order"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Bind("order", None)), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return return"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Bind("return", None)), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return limit"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Bind("limit", None)), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return asc"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Bind("asc", None)), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return desc"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Bind("desc", None)), limit: None })"#);
+
+    // Return JSON
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return false"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Value(Bool(false))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return true"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Value(Bool(true))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return null"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Value(Null)), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return 123"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Value(Number(123))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return 123.456"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Value(Number(123.456))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return "true""#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Value(String("true"))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return {}"#)),
+              r#"Ok(Query { find: All, order: [], ret: Some(Object([])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return { }"#)),
+              r#"Ok(Query { find: All, order: [], ret: Some(Object([])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return {"hello": true}"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Object([("hello", Value(Bool(true)))])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return {hello: true}"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Object([("hello", Value(Bool(true)))])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return []"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Array([])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return [ ]"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Array([])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return [null, 12]"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Array([Value(Null), Value(Number(12))])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return {hello: true, another: "one"}"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Object([("hello", Value(Bool(true))), ("another", Value(String("one")))])), limit: None })"#);
+
+    // Return default value
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default=null"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(Null) })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default=false"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(Bool(false)) })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default=true"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(Bool(true)) })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default=400"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(Number(400)) })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default=-4.6"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(Number(-4.6)) })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default="world""#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(String("world")) })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default={}"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(Object([])) })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default={ }"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(Object([])) })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default={"world": true}"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(Object([("world", Bool(true))])) })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default={"world": {"nested": 12}}"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(Object([("world", Object([("nested", Number(12))]))])) })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default={world: {nested: 12}}"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(Object([("world", Object([("nested", Number(12))]))])) })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default={"a b": true, c: [null]}"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(Object([("a b", Bool(true)), ("c", Array([Null]))])) })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default=["world"]"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(Array([String("world")])) })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default=["world", null]"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(Array([String("world"), Null])) })), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return .hello default=[{"world": null}]"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Path(Path { parts: [Field("hello")], default: Some(Array([Object([("world", Null)])])) })), limit: None })"#);
+
+    // Limit
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} limit 10"#)),
+               r#"Ok(Query { find: All, order: [], ret: None, limit: Some(10) })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order asc limit 10"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: None, order: Asc }], ret: None, limit: Some(10) })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} order asc return . limit 10"#)),
+               r#"Ok(Query { find: All, order: [OrderKey { by: None, order: Asc }], ret: Some(All), limit: Some(10) })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return . limit 10"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(All), limit: Some(10) })"#);
+
+    // Group
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return [group(.hello)]"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Array([Aggregate(Group(Path { parts: [Field("hello")], default: None }, None))])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return [group(.hello desc)]"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Array([Aggregate(Group(Path { parts: [Field("hello")], default: None }, Desc))])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return [group(.hello default=2)]"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Array([Aggregate(Group(Path { parts: [Field("hello")], default: Some(Number(2)) }, None))])), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return [group(.hello default=2 asc)]"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Array([Aggregate(Group(Path { parts: [Field("hello")], default: Some(Number(2)) }, Asc))])), limit: None })"#);
+
+    // Aggregations
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return array(.hello)"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Aggregate(Array(Path { parts: [Field("hello")], default: None }))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return array_flat(.hello)"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Aggregate(ArrayFlat(Path { parts: [Field("hello")], default: None }))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return avg(.hello)"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Aggregate(Avg(Path { parts: [Field("hello")], default: None }))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return count()"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Aggregate(Count)), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return concat(.hello)"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Aggregate(Concat(Path { parts: [Field("hello")], default: None }, None))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return concat(.hello sep="|")"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Aggregate(Concat(Path { parts: [Field("hello")], default: None }, Some("|")))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return max(.hello)"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Aggregate(Max(Path { parts: [Field("hello")], default: None }))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return max_array(.hello)"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Aggregate(MaxArray(Path { parts: [Field("hello")], default: None }))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return min(.hello)"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Aggregate(Min(Path { parts: [Field("hello")], default: None }))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return min_array(.hello)"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Aggregate(MinArray(Path { parts: [Field("hello")], default: None }))), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return score()"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Score), limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {} return sum(.hello)"#)),
+               r#"Ok(Query { find: All, order: [], ret: Some(Aggregate(Sum(Path { parts: [Field("hello")], default: None }))), limit: None })"#);
+
+    // Parameters
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": == @world}"#)),
+               r#"Ok(Query { find: Equal(Some("hello"), Parameter("world")), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": {"nested": == @world}}"#)),
+               r#"Ok(Query { find: Object("hello", Equal(Some("nested"), Parameter("world"))), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": ~34= @world}"#)),
+               r#"Ok(Query { find: WordMatch(Some("hello"), Some(34), Parameter("world")), order: [], ret: None, limit: None })"#);
+    assert_eq!(format!("{:?}", parse_roundtrip(r#"find {"hello": > @world}"#)),
+               r#"Ok(Query { find: Greater(Some("hello"), Parameter("world")), order: [], ret: None, limit: None })"#);
+}
+
+#[test]
+fn spans() {
+    use ast::{PredicateKind, ProjectionKind, Span};
+
+    let query = r#"find {a: {"b c": ~2= "text"}, d: x::[> 1]} order .e asc return [sum(.e.f[0]), x]"#;
+    let text = |span: Span| &query[span.start..span.end];
+    let parsed = parse_query(query).unwrap();
+    assert_eq!(text(parsed.span()), query);
+
+    let (left, right) = match parsed.find.kind {
+        PredicateKind::And(ref left, ref right) => (left, right),
+        ref other => panic!("unexpected predicate: {:?}", other),
+    };
+    assert_eq!(text(left.span()), r#"a: {"b c": ~2= "text"}"#);
+    match left.kind {
+        PredicateKind::Object(ref key, ref inner) => {
+            assert_eq!(text(key.span()), "a");
+            assert_eq!(text(inner.span()), r#""b c": ~2= "text""#);
+            match inner.kind {
+                PredicateKind::WordMatch(Some(ref key), _, ref value) => {
+                    assert_eq!(text(key.span()), r#""b c""#);
+                    assert_eq!(text(value.span()), r#""text""#);
+                },
+                ref other => panic!("unexpected predicate: {:?}", other),
+            }
+        },
+        ref other => panic!("unexpected predicate: {:?}", other),
+    }
+    match right.kind {
+        PredicateKind::Object(_, ref inner) => match inner.kind {
+            PredicateKind::Bind(ref name, ref array) => {
+                assert_eq!(text(name.span()), "x");
+                assert_eq!(text(array.span()), "[> 1]");
+            },
+            ref other => panic!("unexpected predicate: {:?}", other),
+        },
+        ref other => panic!("unexpected predicate: {:?}", other),
+    }
+
+    assert_eq!(text(parsed.order[0].span()), ".e asc");
+    let ret = parsed.ret.unwrap();
+    assert_eq!(text(ret.span()), "[sum(.e.f[0]), x]");
+    match ret.kind {
+        ProjectionKind::Array(ref values) => {
+            assert_eq!(text(values[0].span()), "sum(.e.f[0])");
+            assert_eq!(text(values[1].span()), "x");
+            match values[0].kind {
+                ProjectionKind::Aggregate(ast::Aggregate::Sum(ref path)) => {
+                    assert_eq!(text(path.span()), ".e.f[0]");
+                    let parts: Vec<&str> = path.parts.iter()
+                        .map(|part| text(part.span()))
+                        .collect();
+                    assert_eq!(parts, vec![".e", ".f", "[0]"]);
+                },
+                ref other => panic!("unexpected projection: {:?}", other),
+            }
+        },
+        ref other => panic!("unexpected projection: {:?}", other),
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize() {
+    let query = parse_query(r#"find {a: == 1.5, b: ~2= "x"} return {n: .c[0]}"#).unwrap();
+    let json = serde_json::to_value(&query).unwrap();
+    assert_eq!(json["find"]["kind"]["and"][0]["kind"]["equal"][1]["kind"],
+               json!({"number": {"float": 1.5}}));
+    assert_eq!(json["find"]["kind"]["and"][1]["kind"]["word_match"][1], json!(2));
+    assert_eq!(json["ret"]["kind"]["object"][0][1]["kind"]["path"]["parts"][1],
+               json!({"kind": {"index": 0}, "span": {"start": 42, "end": 45}}));
+    assert_eq!(json["span"], json!({"start": 0, "end": 46}));
+    assert_eq!(json["limit"], json!(null));
+}
@@ -0,0 +1,237 @@
+//! Boolean simplification of plans
+//!
+//! The grammar nests `&&` and `||` as binary trees and allows negations of negations. The
+//! optimiser flattens them, pushes negations inwards as far as possible (negation normal form),
+//! removes duplicate conditions and folds conditions that always or never match.
+//!
+//! A plan returns the same results before and after optimising. That includes scores and bound
+//! variables, so conditions that score or bind are never dropped or duplicated, and negations are
+//! only pushed into conditions that can't fail with an error.
+
+use ast::ValueKind;
+use plan::{Filter, Plan};
+
+/// Returns the simplified plan
+pub fn optimize(mut plan: Plan) -> Plan {
+    plan.filter = optimize_filter(plan.filter);
+    plan
+}
+
+/// Returns the simplified filter
+pub fn optimize_filter(filter: Filter) -> Filter {
+    match filter {
+        Filter::Not(inner) => negate(optimize_filter(*inner)),
+        Filter::And(filters) => and(filters.into_iter().map(optimize_filter)),
+        Filter::Or(filters) => or(filters.into_iter().map(optimize_filter)),
+        Filter::Boost(boost, inner) => {
+            let inner = optimize_filter(*inner);
+            // Boosting doesn't change a score of zero
+            if is_plain(&inner) {
+                inner
+            } else {
+                Filter::Boost(boost, Box::new(inner))
+            }
+        },
+        Filter::Any(path, name, inner) => match optimize_filter(*inner) {
+            Filter::Nothing => Filter::Nothing,
+            inner => Filter::Any(path, name, Box::new(inner)),
+        },
+        Filter::Bind(path, name, inner) => match optimize_filter(*inner) {
+            Filter::Nothing => Filter::Nothing,
+            inner => Filter::Bind(path, name, Box::new(inner)),
+        },
+        leaf => leaf,
+    }
+}
+
+/// Returns the negation of an already simplified filter
+fn negate(filter: Filter) -> Filter {
+    match filter {
+        Filter::All => Filter::Nothing,
+        Filter::Nothing => Filter::All,
+        // A negation neither scores nor binds, so the inner filter must not either
+        Filter::Not(inner) if is_plain(&inner) => *inner,
+        // The score of a negation is always zero
+        Filter::Boost(_, inner) => negate(*inner),
+        // Conditions of an `&&` are only checked until one doesn't match, those of an `||` are
+        // all checked. That only makes a difference if a condition fails.
+        Filter::And(filters) if !filters.iter().any(may_fail) => {
+            or(filters.into_iter().map(negate))
+        },
+        Filter::Or(filters) if !filters.iter().any(may_fail) => {
+            and(filters.into_iter().map(negate))
+        },
+        filter => Filter::Not(Box::new(filter)),
+    }
+}
+
+fn and<I: Iterator<Item = Filter>>(filters: I) -> Filter {
+    let mut flat = Vec::new();
+    for filter in filters {
+        match filter {
+            Filter::And(nested) => flat.extend(nested),
+            other => flat.push(other),
+        }
+    }
+    flat = dedup(flat, &Filter::All);
+    if flat.contains(&Filter::Nothing) && flat.iter().all(is_plain) {
+        return Filter::Nothing;
+    }
+    match flat.len() {
+        0 => Filter::All,
+        1 => flat.pop().expect("there is one filter"),
+        _ => Filter::And(flat),
+    }
+}
+
+fn or<I: Iterator<Item = Filter>>(filters: I) -> Filter {
+    let mut flat = Vec::new();
+    for filter in filters {
+        match filter {
+            Filter::Or(nested) => flat.extend(nested),
+            other => flat.push(other),
+        }
+    }
+    flat = dedup(flat, &Filter::Nothing);
+    if flat.contains(&Filter::All) && flat.iter().all(is_plain) {
+        return Filter::All;
+    }
+    match flat.len() {
+        0 => Filter::Nothing,
+        1 => flat.pop().expect("there is one filter"),
+        _ => Filter::Or(flat),
+    }
+}
+
+/// Removes the neutral filter and repetitions of filters that don't score or bind
+fn dedup(filters: Vec<Filter>, neutral: &Filter) -> Vec<Filter> {
+    let mut unique: Vec<Filter> = Vec::with_capacity(filters.len());
+    for filter in filters {
+        if filter != *neutral && !(is_plain(&filter) && unique.contains(&filter)) {
+            unique.push(filter);
+        }
+    }
+    unique
+}
+
+/// Whether the filter neither scores, nor binds variables, nor fails
+fn is_plain(filter: &Filter) -> bool {
+    !may_fail(filter) && !scores_or_binds(filter)
+}
+
+fn scores_or_binds(filter: &Filter) -> bool {
+    match *filter {
+        Filter::WordMatch(..) | Filter::Bind(..) | Filter::Any(_, Some(_), _) => true,
+        Filter::Any(_, None, ref inner) | Filter::Boost(_, ref inner) => scores_or_binds(inner),
+        // Negations drop both
+        Filter::Not(_) => false,
+        Filter::And(ref filters) | Filter::Or(ref filters) => filters.iter().any(scores_or_binds),
+        Filter::All | Filter::Nothing | Filter::Exists(_) | Filter::Compare(..) |
+        Filter::Intersect(..) => false,
+    }
+}
+
+/// Whether evaluating the filter may return an error, e.g. because of an unbound parameter
+pub fn may_fail(filter: &Filter) -> bool {
+    match *filter {
+        Filter::Compare(_, _, ref value) | Filter::WordMatch(_, _, ref value) => {
+            matches!(value.kind, ValueKind::Parameter(_))
+        },
+        Filter::Intersect(..) => true,
+        Filter::Any(_, _, ref inner) | Filter::Bind(_, _, ref inner) | Filter::Not(ref inner) |
+        Filter::Boost(_, ref inner) => may_fail(inner),
+        Filter::And(ref filters) | Filter::Or(ref filters) => filters.iter().any(may_fail),
+        Filter::All | Filter::Nothing | Filter::Exists(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::Value as Json;
+
+    use ast::{JsonNumber, Value, ValueKind};
+    use eval::evaluate_plan;
+    use parse_query;
+    use plan::{lower, CompareOp};
+    use score::Bm25;
+
+    fn optimized(query: &str) -> Filter {
+        optimize(lower(&parse_query(query).unwrap())).filter
+    }
+
+    fn compare(key: &str) -> Filter {
+        let one = Value::from(ValueKind::Number(JsonNumber::UInt(1)));
+        Filter::Compare(vec![key.to_string()], CompareOp::Equal, one)
+    }
+
+    fn not(filter: Filter) -> Filter {
+        Filter::Not(Box::new(filter))
+    }
+
+    #[test]
+    fn flatten_and_negate() {
+        assert_eq!(optimized("find {a: == 1, b: == 1, c: == 1}"),
+                   Filter::And(vec![compare("a"), compare("b"), compare("c")]));
+        assert_eq!(optimized("find {a: == 1} || {b: == 1} || {c: == 1}"),
+                   Filter::Or(vec![compare("a"), compare("b"), compare("c")]));
+        assert_eq!(optimized("find !{a: != 1}"), compare("a"));
+        assert_eq!(optimized("find !({a: == 1, b: == 1} || {c: != 1})"),
+                   Filter::And(vec![Filter::Or(vec![not(compare("a")), not(compare("b"))]),
+                                    compare("c")]));
+        assert_eq!(optimized("find {a: == 1, a: == 1} || {a: == 1}"), compare("a"));
+    }
+
+    #[test]
+    fn fold_constants() {
+        let all = || Filter::All;
+        assert_eq!(optimized("find {}"), Filter::All);
+        assert_eq!(optimize_filter(Filter::Or(vec![all(), compare("a")])), Filter::All);
+        assert_eq!(optimize_filter(Filter::And(vec![all(), compare("a")])), compare("a"));
+        assert_eq!(optimize_filter(Filter::Or(vec![not(all()), compare("a")])), compare("a"));
+        assert_eq!(optimize_filter(Filter::And(vec![compare("a"), not(all())])), Filter::Nothing);
+        let any = Filter::Any(vec![], None, Box::new(Filter::And(vec![compare("a"), not(all())])));
+        assert_eq!(optimize_filter(any), Filter::Nothing);
+    }
+
+    #[test]
+    fn keep_scores_bindings_and_errors() {
+        let word = "find {a: ~= \"x\", a: ~= \"x\"}";
+        assert!(matches!(optimized(word), Filter::And(ref filters) if filters.len() == 2));
+        assert!(matches!(optimized("find !{a: !~= \"x\"}"), Filter::Not(_)));
+        let bind = lower(&parse_query("find {a: x::[== 1]}").unwrap()).filter;
+        assert!(matches!(optimize_filter(Filter::Or(vec![Filter::All, bind])), Filter::Or(_)));
+        assert!(matches!(optimized("find !({a: == @p} || {b: == 1})"), Filter::Not(_)));
+    }
+
+    #[test]
+    fn same_results() {
+        let documents: Vec<Json> = serde_json::from_str(r#"[
+            {"_id": 1, "a": 1, "b": {"c": "x y"}, "d": [1, 2], "e": [{"f": 1}, {"f": 2}]},
+            {"_id": 2, "a": 2, "b": {"c": "y z"}, "d": [], "e": [{"f": 3}]},
+            {"_id": 3, "a": "1", "b": {}, "e": [{"g": 1}]},
+            {"_id": 4}
+        ]"#).unwrap();
+        let queries = [
+            "find {}",
+            "find !{a: != 1}",
+            "find !({a: == 1} || {b: {c: ~= \"y\"}})",
+            "find !({a: > 1} && !{d: [== 1]})",
+            "find {b: {c: !~= \"x\" || c: ~= \"z\"}^3} order score() desc return score()",
+            "find {a: == 1} || {b: {c: ~= \"y\"}} || {a: == 1} return score()",
+            "find {a: == 1, a: == 1} || {a: == 1} || !{a: == 2}",
+            "find {e: x::[{f: > 1} || {f: > 1}]} return x",
+            "find {e: x::[{f: != 1, f: != 1}], e: y::[{g: == 1}]} return [x, y]",
+            "find {b: {c: ~= \"y\"}^2, b: {c: ~= \"y\"}^2} return score()",
+            "find !(!{d: ![== 2]} || {a: == \"1\"}) return ._id",
+        ];
+        for query in queries.iter() {
+            let plan = lower(&parse_query(query).unwrap());
+            let expected = evaluate_plan(&plan, &documents, &Bm25::default());
+            let actual = evaluate_plan(&optimize(plan.clone()), &documents, &Bm25::default());
+            assert_eq!(actual, expected, "{}", query);
+            assert_ne!(expected, Ok(Vec::new()), "{}", query);
+        }
+    }
+}
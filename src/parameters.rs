@@ -0,0 +1,239 @@
+//! Substitution of `@name` parameters with values
+//!
+//! The values are put into the query as typed values, they are never parsed, so a value can't
+//! change the structure of the query.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+use ast::{JsonValue, Predicate, PredicateKind, Query, Value, ValueKind};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParameterError {
+    /// The query uses a parameter no value was given for
+    Missing(String),
+    /// A value was given for a parameter the query doesn't use
+    Unused(String),
+    /// The value can't be used with the operator of the parameter
+    InvalidType {
+        name: String,
+        operator: &'static str,
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParameterError::Missing(ref name) => write!(f, "no value for parameter @{}", name),
+            ParameterError::Unused(ref name) => write!(f, "parameter @{} is not used", name),
+            ParameterError::InvalidType { ref name, operator, expected } => {
+                write!(f, "parameter @{} is used with `{}` and must be {}", name, operator,
+                       expected)
+            },
+        }
+    }
+}
+
+impl Error for ParameterError {}
+
+/// Returns a copy of the query with all parameters replaced by the given values. If anything
+/// doesn't fit, all problems are returned: the missing parameters in the order they appear in
+/// the query, followed by the values of the wrong type and the unused ones sorted by name.
+pub fn bind_parameters(query: &Query, parameters: &HashMap<String, JsonValue>)
+                       -> Result<Query, Vec<ParameterError>> {
+    let mut binder = Binder {
+        parameters,
+        used: HashSet::new(),
+        errors: Vec::new(),
+    };
+    let find = binder.predicate(&query.find, false);
+
+    let mut unused: Vec<&String> = parameters.keys()
+        .filter(|name| !binder.used.contains(&name[..]))
+        .collect();
+    unused.sort();
+    binder.errors.sort_by_key(|error| match *error {
+        ParameterError::Missing(_) => 0,
+        _ => 1,
+    });
+    binder.errors.extend(unused.into_iter().map(|name| ParameterError::Unused(name.clone())));
+
+    if binder.errors.is_empty() {
+        Ok(Query {
+            find,
+            ..query.clone()
+        })
+    } else {
+        Err(binder.errors)
+    }
+}
+
+/// The types of values an operator accepts
+#[derive(Clone, Copy)]
+enum Accepts {
+    Scalar,
+    Number,
+    String,
+}
+
+struct Binder<'a> {
+    parameters: &'a HashMap<String, JsonValue>,
+    used: HashSet<&'a str>,
+    errors: Vec<ParameterError>,
+}
+
+impl<'a> Binder<'a> {
+    /// `negated` is set if the predicate is directly within a `Not`
+    fn predicate(&mut self, predicate: &Predicate, negated: bool) -> Predicate {
+        let kind = match predicate.kind {
+            PredicateKind::All => PredicateKind::All,
+            PredicateKind::Object(ref key, ref inner) => {
+                PredicateKind::Object(key.clone(), Box::new(self.predicate(inner, false)))
+            },
+            PredicateKind::Array(ref inner) => {
+                PredicateKind::Array(Box::new(self.predicate(inner, false)))
+            },
+            PredicateKind::Bind(ref name, ref inner) => {
+                PredicateKind::Bind(name.clone(), Box::new(self.predicate(inner, false)))
+            },
+            PredicateKind::Not(ref inner) => {
+                PredicateKind::Not(Box::new(self.predicate(inner, true)))
+            },
+            PredicateKind::Boost(boost, ref inner) => {
+                PredicateKind::Boost(boost, Box::new(self.predicate(inner, false)))
+            },
+            PredicateKind::Equal(ref key, ref value) => {
+                let operator = if negated { "!=" } else { "==" };
+                PredicateKind::Equal(key.clone(), self.value(value, operator, Accepts::Scalar))
+            },
+            PredicateKind::Greater(ref key, ref value) => {
+                PredicateKind::Greater(key.clone(), self.value(value, ">", Accepts::Number))
+            },
+            PredicateKind::GreaterEqual(ref key, ref value) => {
+                PredicateKind::GreaterEqual(key.clone(), self.value(value, ">=", Accepts::Number))
+            },
+            PredicateKind::Intersect(ref key, ref bbox) => {
+                PredicateKind::Intersect(key.clone(), bbox.clone())
+            },
+            PredicateKind::Less(ref key, ref value) => {
+                PredicateKind::Less(key.clone(), self.value(value, "<", Accepts::Number))
+            },
+            PredicateKind::LessEqual(ref key, ref value) => {
+                PredicateKind::LessEqual(key.clone(), self.value(value, "<=", Accepts::Number))
+            },
+            PredicateKind::WordMatch(ref key, distance, ref value) => {
+                let operator = match (negated, distance) {
+                    (false, None) => "~=",
+                    (false, Some(_)) => "~N=",
+                    (true, None) => "!~=",
+                    (true, Some(_)) => "!~N=",
+                };
+                let value = self.value(value, operator, Accepts::String);
+                PredicateKind::WordMatch(key.clone(), distance, value)
+            },
+            PredicateKind::And(ref left, ref right) => {
+                PredicateKind::And(Box::new(self.predicate(left, false)),
+                                   Box::new(self.predicate(right, false)))
+            },
+            PredicateKind::Or(ref left, ref right) => {
+                PredicateKind::Or(Box::new(self.predicate(left, false)),
+                                  Box::new(self.predicate(right, false)))
+            },
+        };
+        Predicate::new(kind, predicate.span)
+    }
+
+    fn value(&mut self, value: &Value, operator: &'static str, accepts: Accepts) -> Value {
+        let name = match value.kind {
+            ValueKind::Parameter(ref name) => name,
+            _ => return value.clone(),
+        };
+        let (name, given) = match self.parameters.get_key_value(name) {
+            Some(found) => found,
+            None => {
+                let missing = ParameterError::Missing(name.clone());
+                if !self.errors.contains(&missing) {
+                    self.errors.push(missing);
+                }
+                return value.clone();
+            },
+        };
+        self.used.insert(name);
+
+        let substituted = match (accepts, given) {
+            (Accepts::Scalar, JsonValue::Null) => Some(ValueKind::Null),
+            (Accepts::Scalar, JsonValue::Bool(bool)) => Some(ValueKind::Bool(*bool)),
+            (Accepts::Scalar, JsonValue::Number(number)) |
+            (Accepts::Number, JsonValue::Number(number)) => Some(ValueKind::Number(number.clone())),
+            (Accepts::Scalar, JsonValue::String(string)) |
+            (Accepts::String, JsonValue::String(string)) => Some(ValueKind::String(string.clone())),
+            _ => None,
+        };
+        substituted.map(|kind| Value::new(kind, value.span)).unwrap_or_else(|| {
+            self.errors.push(ParameterError::InvalidType {
+                name: name.clone(),
+                operator,
+                expected: match accepts {
+                    Accepts::Scalar => "null, a boolean, a number or a string",
+                    Accepts::Number => "a number",
+                    Accepts::String => "a string",
+                },
+            });
+            value.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ast::JsonNumber;
+    use parse_query;
+
+    fn bind(query: &str, parameters: Vec<(&str, JsonValue)>) -> Result<Query, Vec<ParameterError>> {
+        let parameters = parameters.into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect();
+        bind_parameters(&parse_query(query).unwrap(), &parameters)
+    }
+
+    #[test]
+    fn substitute() {
+        assert_eq!(bind(r#"find {a: == @a, b: > @b, c: {d: ~2= @c, e: != @a}} || {f: [< @b]}"#,
+                        vec![("a", JsonValue::Null),
+                             ("b", JsonValue::Number(JsonNumber::Float(2.5))),
+                             ("c", JsonValue::String("text".to_string()))]),
+                   Ok(parse_query(r#"find {a: == null, b: > 2.5, c: {d: ~2= "text", e: != null}}
+                                     || {f: [< 2.5]}"#).unwrap()));
+        assert_eq!(bind(r#"find {a: == 1}"#, vec![]),
+                   Ok(parse_query(r#"find {a: == 1}"#).unwrap()));
+        // Values are never parsed as query
+        let injection = "\"} || {x: == 1".to_string();
+        assert_eq!(bind(r#"find {a: == @a}"#, vec![("a", JsonValue::String(injection.clone()))])
+                       .unwrap().find,
+                   Predicate::from(PredicateKind::Equal(Some("a".into()),
+                                                        ValueKind::String(injection).into())));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(bind(r#"find {a: == @a, b: == @b, c: == @a}"#,
+                        vec![("z", JsonValue::Bool(true)), ("y", JsonValue::Null)]),
+                   Err(vec![ParameterError::Missing("a".to_string()),
+                            ParameterError::Missing("b".to_string()),
+                            ParameterError::Unused("y".to_string()),
+                            ParameterError::Unused("z".to_string())]));
+        let errors = bind(r#"find {a: > @a, b: !~= @b, c: != @c}"#,
+                          vec![("a", JsonValue::String("1".to_string())),
+                               ("b", JsonValue::Number(JsonNumber::UInt(1))),
+                               ("c", JsonValue::Array(vec![]))]).unwrap_err();
+        assert_eq!(errors.iter().map(|error| error.to_string()).collect::<Vec<_>>(),
+                   vec!["parameter @a is used with `>` and must be a number",
+                        "parameter @b is used with `!~=` and must be a string",
+                        "parameter @c is used with `!=` and must be null, a boolean, a number \
+                         or a string"]);
+    }
+}
@@ -0,0 +1,273 @@
+//! The logical plan of a query
+//!
+//! The syntax tree follows the grammar, e.g. the key `a.b` of `{a: {b: == 1}}` is spread across an
+//! `Object` and an `Equal` node. The plan is what a query means instead: every condition has the
+//! full key path to the value it checks and arrays are explicit scopes. Evaluators and other
+//! backends run the plan, `lower()` creates it from a query.
+
+use aggregate::has_aggregates;
+use ast::{Bbox, Ident, OrderBy, OrderType, Path, Predicate, PredicateKind, Projection, Query,
+          Value};
+
+/// The keys that lead from the value of the current scope to a nested value
+pub type KeyPath = Vec<String>;
+
+/// A query lowered into the steps that run it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plan {
+    /// The condition documents need to fulfil
+    pub filter: Filter,
+    /// The matching documents are sorted by these keys, the first one is the most significant
+    pub sort: Vec<SortKey>,
+    pub output: Output,
+    pub limit: Option<u64>,
+}
+
+/// A condition on a value, the document itself or an element of an array
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    /// Matches everything
+    All,
+    /// Matches nothing
+    Nothing,
+    /// The path leads to a value, no matter which one
+    Exists(KeyPath),
+    Compare(KeyPath, CompareOp, Value),
+    WordMatch(KeyPath, Option<u64>, Value),
+    Intersect(KeyPath, Bbox),
+    /// The path leads to an array with at least one element that matches the filter. Matching
+    /// elements are bound to the variable, if there is one.
+    Any(KeyPath, Option<String>, Box<Filter>),
+    /// The path leads to a value that matches the filter, it's bound to the variable
+    Bind(KeyPath, String, Box<Filter>),
+    Not(Box<Filter>),
+    /// Multiplies the score of the filter
+    Boost(u64, Box<Filter>),
+    /// All filters need to match, they are checked in order
+    And(Vec<Filter>),
+    /// At least one filter needs to match, all of them are checked
+    Or(Vec<Filter>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompareOp {
+    Equal,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SortKey {
+    Path(Path, Direction),
+    Score(Direction),
+}
+
+impl SortKey {
+    pub fn direction(&self) -> Direction {
+        match *self {
+            SortKey::Path(_, direction) | SortKey::Score(direction) => direction,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Ascending,
+    Descending,
+}
+
+/// What is returned for the matching documents
+#[derive(Clone, Debug, PartialEq)]
+pub enum Output {
+    /// Every document as it is
+    Documents,
+    /// The projection of every document
+    Project(Projection),
+    /// The aggregate functions of the projection applied to all documents
+    Aggregate(Projection),
+}
+
+/// Creates the plan of a query
+pub fn lower(query: &Query) -> Plan {
+    let sort = query.order
+        .iter()
+        .filter_map(|key| {
+            let direction = match key.order {
+                OrderType::Desc => Direction::Descending,
+                OrderType::Asc | OrderType::None => Direction::Ascending,
+            };
+            match key.by {
+                Some(OrderBy::Path(ref path)) => Some(SortKey::Path(path.clone(), direction)),
+                Some(OrderBy::Score) => Some(SortKey::Score(direction)),
+                // Without anything to sort by, all documents are equal
+                None => None,
+            }
+        })
+        .collect();
+    let output = match query.ret {
+        Some(ref projection) if has_aggregates(projection) => Output::Aggregate(projection.clone()),
+        Some(ref projection) => Output::Project(projection.clone()),
+        None => Output::Documents,
+    };
+    Plan {
+        filter: lower_filter(&query.find),
+        sort,
+        output,
+        limit: query.limit,
+    }
+}
+
+/// Creates the filter of a find clause
+pub fn lower_filter(predicate: &Predicate) -> Filter {
+    lower_predicate(predicate, &mut Vec::new())
+}
+
+/// Lowers a predicate that applies to the value `path` leads to within the current scope
+fn lower_predicate(predicate: &Predicate, path: &mut KeyPath) -> Filter {
+    match predicate.kind {
+        PredicateKind::All if path.is_empty() => Filter::All,
+        // Objects only match if the key exists
+        PredicateKind::All => Filter::Exists(path.clone()),
+        PredicateKind::Object(ref key, ref inner) => {
+            path.push(key.name.clone());
+            let filter = lower_predicate(inner, path);
+            path.pop();
+            filter
+        },
+        PredicateKind::Array(ref inner) => {
+            Filter::Any(path.clone(), None, Box::new(lower_predicate(inner, &mut Vec::new())))
+        },
+        PredicateKind::Bind(ref name, ref inner) => {
+            let name = name.name.clone();
+            match inner.kind {
+                PredicateKind::Array(ref elements) => {
+                    Filter::Any(path.clone(), Some(name),
+                                Box::new(lower_predicate(elements, &mut Vec::new())))
+                },
+                PredicateKind::Boost(boost, ref boosted) => match boosted.kind {
+                    PredicateKind::Array(ref elements) => {
+                        let any = Filter::Any(path.clone(), Some(name),
+                                              Box::new(lower_predicate(elements, &mut Vec::new())));
+                        Filter::Boost(boost, Box::new(any))
+                    },
+                    _ => Filter::Bind(path.clone(), name,
+                                      Box::new(lower_predicate(inner, &mut Vec::new()))),
+                },
+                _ => Filter::Bind(path.clone(), name,
+                                  Box::new(lower_predicate(inner, &mut Vec::new()))),
+            }
+        },
+        PredicateKind::Not(ref inner) => {
+            let not = Filter::Not(Box::new(lower_predicate(inner, path)));
+            if path.is_empty() {
+                not
+            } else {
+                // The negation doesn't apply to the existence of the object
+                Filter::And(vec![Filter::Exists(path.clone()), not])
+            }
+        },
+        PredicateKind::Boost(boost, ref inner) => {
+            Filter::Boost(boost, Box::new(lower_predicate(inner, path)))
+        },
+        PredicateKind::Equal(ref key, ref value) => compare(path, key, CompareOp::Equal, value),
+        PredicateKind::Greater(ref key, ref value) => compare(path, key, CompareOp::Greater, value),
+        PredicateKind::GreaterEqual(ref key, ref value) => {
+            compare(path, key, CompareOp::GreaterEqual, value)
+        },
+        PredicateKind::Less(ref key, ref value) => compare(path, key, CompareOp::Less, value),
+        PredicateKind::LessEqual(ref key, ref value) => {
+            compare(path, key, CompareOp::LessEqual, value)
+        },
+        PredicateKind::WordMatch(ref key, distance, ref value) => {
+            Filter::WordMatch(with_key(path, key), distance, value.clone())
+        },
+        PredicateKind::Intersect(ref key, ref bbox) => {
+            Filter::Intersect(with_key(path, key), bbox.clone())
+        },
+        PredicateKind::And(ref left, ref right) => {
+            Filter::And(vec![lower_predicate(left, path), lower_predicate(right, path)])
+        },
+        PredicateKind::Or(ref left, ref right) => {
+            Filter::Or(vec![lower_predicate(left, path), lower_predicate(right, path)])
+        },
+    }
+}
+
+fn compare(path: &KeyPath, key: &Option<Ident>, op: CompareOp, value: &Value) -> Filter {
+    Filter::Compare(with_key(path, key), op, value.clone())
+}
+
+/// The path extended by the key of a condition, conditions within arrays have no key
+fn with_key(path: &KeyPath, key: &Option<Ident>) -> KeyPath {
+    let mut path = path.clone();
+    if let Some(ref key) = *key {
+        path.push(key.name.clone());
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ast::{JsonNumber, ValueKind};
+    use parse_query;
+
+    fn filter(query: &str) -> Filter {
+        lower(&parse_query(query).unwrap()).filter
+    }
+
+    fn path(keys: &[&str]) -> KeyPath {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    fn number(uint: u64) -> Value {
+        Value::from(ValueKind::Number(JsonNumber::UInt(uint)))
+    }
+
+    #[test]
+    fn key_paths() {
+        assert_eq!(filter("find {}"), Filter::All);
+        assert_eq!(filter("find {a: {b: {c: == 1}}}"),
+                   Filter::Compare(path(&["a", "b", "c"]), CompareOp::Equal, number(1)));
+        assert_eq!(filter("find {a: {b: == 1, c: != 2}}"),
+                   Filter::And(vec![
+                       Filter::Compare(path(&["a", "b"]), CompareOp::Equal, number(1)),
+                       Filter::And(vec![
+                           Filter::Exists(path(&["a"])),
+                           Filter::Not(Box::new(Filter::Compare(path(&["a", "c"]),
+                                                                CompareOp::Equal, number(2)))),
+                       ]),
+                   ]));
+    }
+
+    #[test]
+    fn array_scopes() {
+        assert_eq!(filter("find {a: {b: [{c: > 1}]}}"),
+                   Filter::Any(path(&["a", "b"]), None,
+                               Box::new(Filter::Compare(path(&["c"]), CompareOp::Greater,
+                                                        number(1)))));
+        assert_eq!(filter("find {a: x::[== 1]^2}"),
+                   Filter::Boost(2, Box::new(Filter::Any(
+                       path(&["a"]), Some("x".to_string()),
+                       Box::new(Filter::Compare(path(&[]), CompareOp::Equal, number(1)))))));
+        assert_eq!(filter("find {a: x::![== 1]}"),
+                   Filter::Bind(path(&["a"]), "x".to_string(), Box::new(Filter::Not(Box::new(
+                       Filter::Any(path(&[]), None, Box::new(Filter::Compare(
+                           path(&[]), CompareOp::Equal, number(1)))))))));
+    }
+
+    #[test]
+    fn sort_output_limit() {
+        let query = "find {} order .a desc, score() return count() limit 3";
+        let plan = lower(&parse_query(query).unwrap());
+        assert!(matches!(plan.sort[..],
+                         [SortKey::Path(_, Direction::Descending),
+                          SortKey::Score(Direction::Ascending)]));
+        assert!(matches!(plan.output, Output::Aggregate(_)));
+        assert_eq!(plan.limit, Some(3));
+        assert_eq!(lower(&parse_query("find {}").unwrap()).output, Output::Documents);
+    }
+}
@@ -0,0 +1,428 @@
+//! Turns the AST back into the text of a Noise query
+//!
+//! The output is canonical: keys are only quoted when needed, conditions are separated by `, `
+//! and there's a single space around operators. Parsing the printed query results in the same
+//! AST again.
+
+use std::fmt;
+
+use ast::{Aggregate, Bbox, Ident, JsonNumber, JsonValue, OrderBy, OrderKey, OrderType, Path,
+          PathPart, PathPartKind, Predicate, PredicateKind, Projection, ProjectionKind, Query,
+          Value, ValueKind};
+use escape::Escaped;
+
+// Keywords that can't be used as unquoted field names
+const RESERVED: &[&str] = &["true", "false", "null", "default", "sep"];
+
+/// Returns whether the given string is valid as a field name without quotes
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_alphabetic() || first == '_' || first == '$' => {
+            chars.all(|cc| cc.is_alphabetic() || cc.is_numeric() || cc == '_' || cc == '$')
+        },
+        _ => false,
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, string: &str) -> fmt::Result {
+    write!(f, "\"{}\"", Escaped(string))
+}
+
+/// Writes a key of an object, it's only quoted if needed
+fn write_key(f: &mut fmt::Formatter, key: &str) -> fmt::Result {
+    if is_identifier(key) && !RESERVED.contains(&key) {
+        f.write_str(key)
+    } else {
+        write_string(f, key)
+    }
+}
+
+/// Displays a key of an object the same way it is written within a query
+pub struct Key<'a>(pub &'a str);
+
+impl<'a> fmt::Display for Key<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_key(f, self.0)
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.find.kind {
+            PredicateKind::All => f.write_str("find {}")?,
+            _ => write!(f, "find {{{}}}", self.find)?,
+        }
+        if !self.order.is_empty() {
+            f.write_str(" order ")?;
+            for (ii, key) in self.order.iter().enumerate() {
+                if ii > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{}", key)?;
+            }
+        }
+        if let Some(ref ret) = self.ret {
+            write!(f, " return {}", ret)?;
+        }
+        if let Some(limit) = self.limit {
+            write!(f, " limit {}", limit)?;
+        }
+        Ok(())
+    }
+}
+
+/// A predicate is written the way it appears within the curly braces of an object
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_or(f, self)
+    }
+}
+
+fn write_or(f: &mut fmt::Formatter, predicate: &Predicate) -> fmt::Result {
+    match predicate.kind {
+        PredicateKind::Or(ref left, ref right) => {
+            write_or(f, left)?;
+            f.write_str(" || ")?;
+            write_and(f, right)
+        },
+        _ => write_and(f, predicate),
+    }
+}
+
+fn write_and(f: &mut fmt::Formatter, predicate: &Predicate) -> fmt::Result {
+    match predicate.kind {
+        PredicateKind::And(ref left, ref right) => {
+            write_and(f, left)?;
+            f.write_str(", ")?;
+            write_key_value(f, right)
+        },
+        _ => write_key_value(f, predicate),
+    }
+}
+
+/// Returns whether the predicate is a single operator with its value
+fn is_comparison(predicate: &Predicate) -> bool {
+    match predicate.kind {
+        PredicateKind::Equal(..) |
+        PredicateKind::Greater(..) |
+        PredicateKind::GreaterEqual(..) |
+        PredicateKind::Intersect(..) |
+        PredicateKind::Less(..) |
+        PredicateKind::LessEqual(..) |
+        PredicateKind::WordMatch(..) => true,
+        PredicateKind::Not(ref inner) => {
+            matches!(inner.kind, PredicateKind::Equal(..) | PredicateKind::WordMatch(..))
+        },
+        _ => false,
+    }
+}
+
+fn write_comparison(f: &mut fmt::Formatter, key: &Option<Ident>, operator: &str,
+                    value: &dyn fmt::Display) -> fmt::Result {
+    if let Some(ref key) = *key {
+        write_key(f, &key.name)?;
+        f.write_str(": ")?;
+    }
+    write!(f, "{} {}", operator, value)
+}
+
+fn word_match_operator(not: bool, proximity: Option<u64>) -> String {
+    let not = if not { "!" } else { "" };
+    match proximity {
+        Some(proximity) => format!("{}~{}=", not, proximity),
+        None => format!("{}~=", not),
+    }
+}
+
+fn write_key_value(f: &mut fmt::Formatter, predicate: &Predicate) -> fmt::Result {
+    match predicate.kind {
+        PredicateKind::All => Ok(()),
+        PredicateKind::Object(ref key, ref value) => {
+            write_key(f, &key.name)?;
+            f.write_str(": ")?;
+            write_value(f, value)
+        },
+        // Arrays and binds are only valid as the value of an object
+        PredicateKind::Array(_) | PredicateKind::Bind(..) => write_value(f, predicate),
+        PredicateKind::Not(ref inner) => match inner.kind {
+            PredicateKind::Equal(ref key, ref value) => write_comparison(f, key, "!=", value),
+            PredicateKind::WordMatch(ref key, proximity, ref value) => {
+                write_comparison(f, key, &word_match_operator(true, proximity), value)
+            },
+            _ => write!(f, "!({})", inner),
+        },
+        PredicateKind::Boost(boost, ref inner) => {
+            if is_comparison(inner) {
+                write_key_value(f, inner)?;
+            } else {
+                write!(f, "({})", inner)?;
+            }
+            write!(f, "^{}", boost)
+        },
+        PredicateKind::Equal(ref key, ref value) => write_comparison(f, key, "==", value),
+        PredicateKind::Greater(ref key, ref value) => write_comparison(f, key, ">", value),
+        PredicateKind::GreaterEqual(ref key, ref value) => write_comparison(f, key, ">=", value),
+        PredicateKind::Intersect(ref key, ref bbox) => write_comparison(f, key, "&&", bbox),
+        PredicateKind::Less(ref key, ref value) => write_comparison(f, key, "<", value),
+        PredicateKind::LessEqual(ref key, ref value) => write_comparison(f, key, "<=", value),
+        PredicateKind::WordMatch(ref key, proximity, ref value) => {
+            write_comparison(f, key, &word_match_operator(false, proximity), value)
+        },
+        PredicateKind::And(..) | PredicateKind::Or(..) => write!(f, "({})", predicate),
+    }
+}
+
+/// Writes the value of a key within an object, it's either an object or an array
+fn write_value(f: &mut fmt::Formatter, predicate: &Predicate) -> fmt::Result {
+    match predicate.kind {
+        PredicateKind::Not(ref inner) => {
+            f.write_str("!")?;
+            write_boosted_value(f, inner)
+        },
+        _ => write_boosted_value(f, predicate),
+    }
+}
+
+fn write_boosted_value(f: &mut fmt::Formatter, predicate: &Predicate) -> fmt::Result {
+    match predicate.kind {
+        PredicateKind::Boost(boost, ref inner) => {
+            write_plain_value(f, inner)?;
+            write!(f, "^{}", boost)
+        },
+        _ => write_plain_value(f, predicate),
+    }
+}
+
+fn write_plain_value(f: &mut fmt::Formatter, predicate: &Predicate) -> fmt::Result {
+    match predicate.kind {
+        PredicateKind::Array(ref inner) => {
+            f.write_str("[")?;
+            write_array(f, inner)?;
+            f.write_str("]")
+        },
+        PredicateKind::Bind(ref name, ref inner) => {
+            write!(f, "{}::", name)?;
+            write_value(f, inner)
+        },
+        _ => write!(f, "{{{}}}", predicate),
+    }
+}
+
+fn write_array(f: &mut fmt::Formatter, predicate: &Predicate) -> fmt::Result {
+    match predicate.kind {
+        PredicateKind::Array(ref inner) => {
+            f.write_str("[")?;
+            write_array(f, inner)?;
+            f.write_str("]")
+        },
+        _ => write_or(f, predicate),
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ValueKind::Null => f.write_str("null"),
+            ValueKind::Bool(bool) => write!(f, "{}", bool),
+            ValueKind::Number(ref number) => write!(f, "{}", number),
+            ValueKind::String(ref string) => write_string(f, string),
+            ValueKind::Parameter(ref name) => write!(f, "@{}", name),
+        }
+    }
+}
+
+impl fmt::Display for JsonNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JsonNumber::Int(int) => write!(f, "{}", int),
+            JsonNumber::UInt(uint) => write!(f, "{}", uint),
+            // `Debug` keeps the fraction of whole numbers and uses an exponent for large and
+            // small ones, so that the number is parsed as `Float` again
+            JsonNumber::Float(float) => write!(f, "{:?}", float),
+            JsonNumber::Big(ref text) => f.write_str(text),
+        }
+    }
+}
+
+impl fmt::Display for Bbox {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}, {}, {}, {}]", self.west, self.south, self.east, self.north)
+    }
+}
+
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JsonValue::Null => f.write_str("null"),
+            JsonValue::Bool(bool) => write!(f, "{}", bool),
+            JsonValue::Number(ref number) => write!(f, "{}", number),
+            JsonValue::String(ref string) => write_string(f, string),
+            JsonValue::Array(ref values) => {
+                f.write_str("[")?;
+                for (ii, value) in values.iter().enumerate() {
+                    if ii > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_str("]")
+            },
+            JsonValue::Object(ref members) => {
+                f.write_str("{")?;
+                for (ii, (key, value)) in members.iter().enumerate() {
+                    if ii > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write_key(f, key)?;
+                    write!(f, ": {}", value)?;
+                }
+                f.write_str("}")
+            },
+        }
+    }
+}
+
+impl fmt::Display for OrderType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OrderType::None => Ok(()),
+            OrderType::Asc => f.write_str("asc"),
+            OrderType::Desc => f.write_str("desc"),
+        }
+    }
+}
+
+impl fmt::Display for OrderKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.by {
+            Some(OrderBy::Path(ref path)) => write!(f, "{}", path)?,
+            Some(OrderBy::Score) => f.write_str("score()")?,
+            None => return write!(f, "{}", self.order),
+        }
+        match self.order {
+            OrderType::None => Ok(()),
+            ref order => write!(f, " {}", order),
+        }
+    }
+}
+
+impl fmt::Display for Projection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ProjectionKind::All => f.write_str("."),
+            ProjectionKind::Value(ref value) => write!(f, "{}", value),
+            ProjectionKind::Path(ref path) => write!(f, "{}", path),
+            ProjectionKind::Bind(ref name, Some(ref path)) => write!(f, "{}{}", name, path),
+            ProjectionKind::Bind(ref name, None) => f.write_str(&name.name),
+            ProjectionKind::Object(ref members) => {
+                f.write_str("{")?;
+                for (ii, (key, value)) in members.iter().enumerate() {
+                    if ii > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write_key(f, &key.name)?;
+                    write!(f, ": {}", value)?;
+                }
+                f.write_str("}")
+            },
+            ProjectionKind::Array(ref values) => {
+                f.write_str("[")?;
+                for (ii, value) in values.iter().enumerate() {
+                    if ii > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_str("]")
+            },
+            ProjectionKind::Aggregate(ref aggregate) => write!(f, "{}", aggregate),
+            ProjectionKind::Score => f.write_str("score()"),
+        }
+    }
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Aggregate::Array(ref path) => write!(f, "array({})", path),
+            Aggregate::ArrayFlat(ref path) => write!(f, "array_flat({})", path),
+            Aggregate::Avg(ref path) => write!(f, "avg({})", path),
+            Aggregate::Count => f.write_str("count()"),
+            Aggregate::Concat(ref path, Some(ref sep)) => {
+                write!(f, "concat({} sep=", path)?;
+                write_string(f, sep)?;
+                f.write_str(")")
+            },
+            Aggregate::Concat(ref path, None) => write!(f, "concat({})", path),
+            Aggregate::Group(ref path, OrderType::None) => write!(f, "group({})", path),
+            Aggregate::Group(ref path, ref order) => write!(f, "group({} {})", path, order),
+            Aggregate::Max(ref path) => write!(f, "max({})", path),
+            Aggregate::MaxArray(ref path) => write!(f, "max_array({})", path),
+            Aggregate::Min(ref path) => write!(f, "min({})", path),
+            Aggregate::MinArray(ref path) => write!(f, "min_array({})", path),
+            Aggregate::Sum(ref path) => write!(f, "sum({})", path),
+        }
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for part in &self.parts {
+            write!(f, "{}", part)?;
+        }
+        if let Some(ref default) = self.default {
+            write!(f, " default={}", default)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PathPart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            PathPartKind::Field(ref name) if is_identifier(name) => write!(f, ".{}", name),
+            PathPartKind::Field(ref name) => {
+                f.write_str("[")?;
+                write_string(f, name)?;
+                f.write_str("]")
+            },
+            PathPartKind::Index(index) => write!(f, "[{}]", index),
+            PathPartKind::Array => f.write_str("[]"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parse_query;
+
+    fn print(query: &str) -> String {
+        parse_query(query).unwrap().to_string()
+    }
+
+    #[test]
+    fn canonical() {
+        assert_eq!(print(r#"find {"hello":=="world"}"#), r#"find {hello: == "world"}"#);
+        assert_eq!(print(r#"find {"hello world": == 1 && "true": == true}"#),
+                   r#"find {"hello world": == 1, "true": == true}"#);
+        assert_eq!(print(r#"find {a: == 1} || {b: == 2}"#), r#"find {a: == 1 || b: == 2}"#);
+        assert_eq!(print(r#"find {(a: == 1 || b: == 2), c: ~3= "x"}"#),
+                   r#"find {(a: == 1 || b: == 2), c: ~3= "x"}"#);
+        assert_eq!(print(r#"find {a: != 1, b: !~= "x", c: !{d: > 2}}"#),
+                   r#"find {a: != 1, b: !~= "x", c: !{d: > 2}}"#);
+        assert_eq!(print(r#"find {a: x::[{b: == 1}]^2, c: && [1, 2, 3, 4]}"#),
+                   r#"find {a: x::[b: == 1]^2, c: && [1, 2, 3, 4]}"#);
+    }
+
+    #[test]
+    fn order_return_limit() {
+        assert_eq!(print(r#"find {} order .a desc, score() return {"a b": .a["b c"][0][], c: x.y}
+                            limit 3"#),
+                   r#"find {} order .a desc, score() return {"a b": .a["b c"][0][], c: x.y} limit 3"#);
+        assert_eq!(print(r#"find {} return [group(.a desc), concat(.b sep="|"), count()]"#),
+                   r#"find {} return [group(.a desc), concat(.b sep="|"), count()]"#);
+        assert_eq!(print(r#"find {} return .a default={"b": [1, null]}"#),
+                   r#"find {} return .a default={b: [1, null]}"#);
+    }
+}
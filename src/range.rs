@@ -0,0 +1,308 @@
+//! Ranges of the values that comparisons allow
+//!
+//! Comparisons on the same field that all need to match, e.g. `{age: > 18, age: < 65}`, are
+//! merged into a single range. If no value is within the range, the comparisons can never match
+//! together. A value also needs to be of the same type as the values it's compared with, so
+//! `{a: > 1, a: < "z"}` never matches either.
+//!
+//! Only comparisons that are combined with `&&` (or `,`) are merged. Each side of an `||` and each
+//! array are looked at on their own, negations are ignored.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use ast::{Path, PathPart, PathPartKind, Predicate, PredicateKind, Span, Value, ValueKind};
+use plan::CompareOp;
+
+/// The lower or upper end of a range
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bound {
+    pub value: Value,
+    /// Whether the value itself is within the range
+    pub inclusive: bool,
+}
+
+/// The values a field may have
+#[derive(Clone, Debug, PartialEq)]
+pub struct Range {
+    /// The field the comparisons are about, `[]` stands for the elements of an array
+    pub path: Path,
+    pub lower: Option<Bound>,
+    pub upper: Option<Bound>,
+    /// The spans of the comparisons the range was merged from
+    pub spans: Vec<Span>,
+    /// The span of the first comparison that made the range empty
+    pub conflict: Option<Span>,
+}
+
+impl Range {
+    /// A range that allows every value
+    pub fn new(path: Path) -> Range {
+        Range {
+            path,
+            lower: None,
+            upper: None,
+            spans: Vec::new(),
+            conflict: None,
+        }
+    }
+
+    /// Whether no value is within the range
+    pub fn is_empty(&self) -> bool {
+        self.conflict.is_some()
+    }
+
+    /// Narrows the range by a comparison
+    pub fn add(&mut self, op: CompareOp, value: &Value, span: Span) {
+        self.spans.push(span);
+        let other_type = self.lower.iter().chain(self.upper.iter())
+            .any(|bound| compare(&bound.value.kind, &value.kind).is_none());
+        if other_type {
+            self.conflict = self.conflict.or(Some(span));
+            return;
+        }
+        let bound = |inclusive| Bound { value: value.clone(), inclusive };
+        match op {
+            CompareOp::Equal => {
+                self.lower = tighter(self.lower.take(), bound(true), Ordering::Greater);
+                self.upper = tighter(self.upper.take(), bound(true), Ordering::Less);
+            },
+            CompareOp::Greater => {
+                self.lower = tighter(self.lower.take(), bound(false), Ordering::Greater);
+            },
+            CompareOp::GreaterEqual => {
+                self.lower = tighter(self.lower.take(), bound(true), Ordering::Greater);
+            },
+            CompareOp::Less => {
+                self.upper = tighter(self.upper.take(), bound(false), Ordering::Less);
+            },
+            CompareOp::LessEqual => {
+                self.upper = tighter(self.upper.take(), bound(true), Ordering::Less);
+            },
+        }
+        if !self.has_values() {
+            self.conflict = self.conflict.or(Some(span));
+        }
+    }
+
+    /// Whether every value within this range is also within the other one
+    pub fn is_within(&self, other: &Range) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        if other.is_empty() {
+            return false;
+        }
+        let lower = match (&self.lower, &other.lower) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(inner), Some(outer)) => match compare(&inner.value.kind, &outer.value.kind) {
+                Some(Ordering::Greater) => true,
+                Some(Ordering::Equal) => outer.inclusive || !inner.inclusive,
+                _ => false,
+            },
+        };
+        let upper = match (&self.upper, &other.upper) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(inner), Some(outer)) => match compare(&inner.value.kind, &outer.value.kind) {
+                Some(Ordering::Less) => true,
+                Some(Ordering::Equal) => outer.inclusive || !inner.inclusive,
+                _ => false,
+            },
+        };
+        // A bound only on one side still restricts the type of the values
+        let same_type = match (self.lower.as_ref().or(self.upper.as_ref()),
+                               other.lower.as_ref().or(other.upper.as_ref())) {
+            (Some(inner), Some(outer)) => compare(&inner.value.kind, &outer.value.kind).is_some(),
+            _ => true,
+        };
+        lower && upper && same_type
+    }
+
+    fn has_values(&self) -> bool {
+        match (&self.lower, &self.upper) {
+            (Some(lower), Some(upper)) => match compare(&lower.value.kind, &upper.value.kind) {
+                Some(Ordering::Less) => true,
+                Some(Ordering::Equal) => lower.inclusive && upper.inclusive,
+                Some(Ordering::Greater) | None => false,
+            },
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for Range {
+    /// Displays the range like `.age > 18 and < 65`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        match (&self.lower, &self.upper) {
+            (Some(lower), Some(upper)) if lower == upper && lower.inclusive => {
+                write!(f, " == {}", lower.value)
+            },
+            (lower, upper) => {
+                if let Some(lower) = lower {
+                    write!(f, " {} {}", if lower.inclusive { ">=" } else { ">" }, lower.value)?;
+                }
+                if lower.is_some() && upper.is_some() {
+                    f.write_str(" and")?;
+                }
+                if let Some(upper) = upper {
+                    write!(f, " {} {}", if upper.inclusive { "<=" } else { "<" }, upper.value)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+/// Returns the bound that allows fewer values. `wanted` is the ordering of the value of the
+/// tighter bound to the other one, both are of the same type.
+fn tighter(current: Option<Bound>, new: Bound, wanted: Ordering) -> Option<Bound> {
+    let current = match current {
+        Some(current) => current,
+        None => return Some(new),
+    };
+    Some(match compare(&new.value.kind, &current.value.kind) {
+        Some(Ordering::Equal) => Bound {
+            inclusive: current.inclusive && new.inclusive,
+            ..current
+        },
+        Some(ordering) if ordering == wanted => new,
+        _ => current,
+    })
+}
+
+/// Compares two values of a query, `None` if they are of different types
+fn compare(aa: &ValueKind, bb: &ValueKind) -> Option<Ordering> {
+    match (aa, bb) {
+        (ValueKind::Null, ValueKind::Null) => Some(Ordering::Equal),
+        (ValueKind::Bool(aa), ValueKind::Bool(bb)) => Some(aa.cmp(bb)),
+        (ValueKind::Number(aa), ValueKind::Number(bb)) => aa.compare(bb),
+        (ValueKind::String(aa), ValueKind::String(bb)) => Some(aa.cmp(bb)),
+        _ => None,
+    }
+}
+
+/// Returns the ranges of all fields that are compared within the predicate
+pub fn ranges(predicate: &Predicate) -> Vec<Range> {
+    let mut all = Vec::new();
+    let mut conjunction = Vec::new();
+    collect(predicate, &mut Vec::new(), &mut conjunction, &mut all);
+    conjunction.extend(all);
+    conjunction
+}
+
+/// Collects the ranges of the comparisons that all need to match into `conjunction`, those of
+/// independent parts of the query into `independent`
+fn collect(predicate: &Predicate, path: &mut Vec<PathPart>, conjunction: &mut Vec<Range>,
+           independent: &mut Vec<Range>) {
+    match predicate.kind {
+        PredicateKind::Object(ref key, ref inner) => {
+            path.push(PathPart::from(PathPartKind::Field(key.name.clone())));
+            collect(inner, path, conjunction, independent);
+            path.pop();
+        },
+        PredicateKind::Array(ref inner) => {
+            path.push(PathPart::from(PathPartKind::Array));
+            collect_independent(inner, path, independent);
+            path.pop();
+        },
+        PredicateKind::Bind(_, ref inner) | PredicateKind::Boost(_, ref inner) => {
+            collect(inner, path, conjunction, independent)
+        },
+        PredicateKind::And(ref left, ref right) => {
+            collect(left, path, conjunction, independent);
+            collect(right, path, conjunction, independent);
+        },
+        PredicateKind::Or(ref left, ref right) => {
+            collect_independent(left, path, independent);
+            collect_independent(right, path, independent);
+        },
+        PredicateKind::Equal(ref key, ref value) |
+        PredicateKind::Greater(ref key, ref value) |
+        PredicateKind::GreaterEqual(ref key, ref value) |
+        PredicateKind::Less(ref key, ref value) |
+        PredicateKind::LessEqual(ref key, ref value) => {
+            if let ValueKind::Parameter(_) = value.kind {
+                return;
+            }
+            let mut parts = path.clone();
+            if let Some(ref key) = *key {
+                parts.push(PathPart::from(PathPartKind::Field(key.name.clone())));
+            }
+            let field = Path { parts, default: None, span: Span::default() };
+            let index = match conjunction.iter().position(|range| range.path == field) {
+                Some(index) => index,
+                None => {
+                    conjunction.push(Range::new(field));
+                    conjunction.len() - 1
+                },
+            };
+            let op = match predicate.kind {
+                PredicateKind::Equal(..) => CompareOp::Equal,
+                PredicateKind::Greater(..) => CompareOp::Greater,
+                PredicateKind::GreaterEqual(..) => CompareOp::GreaterEqual,
+                PredicateKind::Less(..) => CompareOp::Less,
+                _ => CompareOp::LessEqual,
+            };
+            conjunction[index].add(op, value, predicate.span);
+        },
+        PredicateKind::All | PredicateKind::Not(_) | PredicateKind::Intersect(..) |
+        PredicateKind::WordMatch(..) => {},
+    }
+}
+
+/// Collects the ranges of a part of the query that is independent of the rest
+fn collect_independent(predicate: &Predicate, path: &mut Vec<PathPart>,
+                       independent: &mut Vec<Range>) {
+    let mut conjunction = Vec::new();
+    collect(predicate, path, &mut conjunction, independent);
+    independent.extend(conjunction);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use parse_query;
+
+    fn ranges_of(query: &str) -> Vec<String> {
+        ranges(&parse_query(query).unwrap().find)
+            .iter()
+            .map(|range| {
+                let empty = if range.is_empty() { " (empty)" } else { "" };
+                format!("{}{}", range, empty)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merge() {
+        assert_eq!(ranges_of("find {age: > 18, age: < 65, age: >= 20}"),
+                   vec![".age >= 20 and < 65"]);
+        assert_eq!(ranges_of("find {a: {b: >= 1, b: <= 1}, c: == \"x\", c: >= \"a\"}"),
+                   vec![".a.b == 1", ".c == \"x\""]);
+        assert_eq!(ranges_of("find {a: [> 1, < 3], a: [{b: == 1}]} || {a: > 5}"),
+                   vec![".a[] > 1 and < 3", ".a[].b == 1", ".a > 5"]);
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(ranges_of("find {age: > 10, age: < 5}"), vec![".age > 10 and < 5 (empty)"]);
+        assert_eq!(ranges_of("find {x: == 1, x: == 2}"), vec![".x >= 2 and <= 1 (empty)"]);
+        assert_eq!(ranges_of("find {x: > 1, x: < 1}"), vec![".x > 1 and < 1 (empty)"]);
+        assert_eq!(ranges_of("find {x: >= 1, x: < \"a\"}"), vec![".x >= 1 (empty)"]);
+        assert_eq!(ranges_of("find {x: >= 1, x: <= 1}"), vec![".x == 1"]);
+        // Not within the same conjunction
+        assert_eq!(ranges_of("find {x: == 1} || {x: == 2}"), vec![".x == 1", ".x == 2"]);
+    }
+
+    #[test]
+    fn conflict_span() {
+        let query = "find {x: > 10, y: == 1, x: < 5, x: == 1}";
+        let found = ranges(&parse_query(query).unwrap().find);
+        assert_eq!(found[0].conflict, Some(Span::new(24, 30)));
+        assert_eq!(found[0].spans.len(), 3);
+    }
+}
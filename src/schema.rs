@@ -0,0 +1,154 @@
+//! The versioned JSON format of queries
+//!
+//! Queries can be exchanged as JSON instead of Noise text, e.g. a frontend builds the structure
+//! and the backend prints it with `to_string()` or runs it with `eval::evaluate()`. The query is
+//! wrapped in an object that contains the version of the format:
+//!
+//! ```json
+//! {"version": 1, "query": {"find": {"kind": "all"}, "ret": {"kind": {"path": {"parts": []}}}}}
+//! ```
+//!
+//! The version is increased whenever a change would make documents of the previous version be
+//! read differently. Documents with another version are rejected.
+//!
+//! # Version 1
+//!
+//! Every node is an object with its `kind` and optionally its `span`, the byte range
+//! `{"start": 0, "end": 4}` within the query text. A missing span is empty. Kinds are written in
+//! snake case, either as a string if they have no data (`"all"`) or as an object with a single
+//! member whose value is the data (`{"not": {...}}`). Data with several fields is an array.
+//!
+//! - `query`: `find` (a predicate), `order` (array of order keys, may be left out), `ret` (a
+//!   projection or `null`), `limit` (a number or `null`), `comments` (may be left out).
+//! - Predicate kinds: `all`, `object: [ident, predicate]`, `array: predicate`,
+//!   `bind: [ident, predicate]`, `not: predicate`, `boost: [number, predicate]`, the
+//!   comparisons `equal`, `greater`, `greater_equal`, `less` and `less_equal` as
+//!   `[ident or null, value]`, `intersect: [ident or null, bbox]`,
+//!   `word_match: [ident or null, proximity or null, value]`, and `and` and `or` as
+//!   `[predicate, predicate]`. The ident is `null` for operators within an array.
+//! - `ident`: `{"name": "key"}`, optionally with a span.
+//! - Value kinds: `null`, `{"bool": true}`, `{"number": number}`, `{"string": "text"}` and
+//!   `{"parameter": "name"}`.
+//! - `number`: exactly one of `{"int": -1}` (negative), `{"uint": 1}`, `{"float": 1.5}` or
+//!   `{"big": "1.2345678901234567890"}` for numbers that don't fit any of them, as written.
+//! - `bbox`: `{"west": 0.0, "south": 0.0, "east": 1.0, "north": 1.0}`.
+//! - Order key: `{"by": {"path": path} or "score" or null, "order": "none" or "asc" or "desc"}`.
+//! - Projection kinds: `all`, `score`, `{"value": json}`, `{"path": path}`,
+//!   `{"bind": [ident, path or null]}`, `{"object": [[ident, projection], ...]}`,
+//!   `{"array": [projection, ...]}` and `{"aggregate": aggregate}`.
+//! - `path`: `{"parts": [part, ...], "default": json or null}`, a part is a node with the kind
+//!   `{"field": "name"}`, `{"index": 0}` or `array`.
+//! - `json`: `null`, `{"bool": true}`, `{"number": number}`, `{"string": "text"}`,
+//!   `{"array": [json, ...]}` or `{"object": [["key", json], ...]}`.
+//! - Aggregate: `count`, or one of `array`, `array_flat`, `avg`, `max`, `max_array`, `min`,
+//!   `min_array` and `sum` with a path as data, `{"concat": [path, separator or null]}` or
+//!   `{"group": [path, order]}`.
+//! - Comment: `{"text": "# as written", "span": span}`.
+
+use std::error::Error;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+use ast::Query;
+
+/// The version of the format `to_json()` writes and `from_json()` reads
+pub const VERSION: u64 = 1;
+
+#[derive(Serialize)]
+struct Document<'a> {
+    version: u64,
+    query: &'a Query,
+}
+
+#[derive(Deserialize)]
+struct OwnedDocument {
+    version: u64,
+    query: serde_json::Value,
+}
+
+/// The reasons why a query can't be read from JSON
+#[derive(Debug)]
+pub enum SchemaError {
+    /// The document was written for another version of the format
+    UnsupportedVersion(u64),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SchemaError::UnsupportedVersion(version) => {
+                write!(f, "unsupported version {} of the query format, expected {}", version,
+                       VERSION)
+            },
+            SchemaError::Json(ref err) => write!(f, "invalid query: {}", err),
+        }
+    }
+}
+
+impl Error for SchemaError {}
+
+impl From<serde_json::Error> for SchemaError {
+    fn from(err: serde_json::Error) -> SchemaError {
+        SchemaError::Json(err)
+    }
+}
+
+/// Returns the query as JSON document of the current version
+pub fn to_json(query: &Query) -> String {
+    let document = Document { version: VERSION, query };
+    serde_json::to_string(&document).expect("queries can always be serialized")
+}
+
+/// Reads a query from a JSON document. The version is checked before the query is read.
+pub fn from_json(json: &str) -> Result<Query, SchemaError> {
+    let document: OwnedDocument = serde_json::from_str(json)?;
+    if document.version != VERSION {
+        return Err(SchemaError::UnsupportedVersion(document.version));
+    }
+    Ok(serde_json::from_value(document.query)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use parse_query;
+
+    #[test]
+    fn roundtrip() {
+        let query = parse_query(r#"find {a: == 1.5, b: [~2= "x"], c: && [0, 0, 1, 1]}
+                                   order .c desc return {n: .c[0] default=[1], s: score()}
+                                   limit 5"#).unwrap();
+        assert_eq!(from_json(&to_json(&query)).unwrap(), query);
+    }
+
+    #[test]
+    fn without_spans() {
+        let json = r#"{"version": 1, "query": {
+            "find": {"kind": {"equal": [{"name": "a"}, {"kind": {"number": {"uint": 1}}}]}},
+            "ret": {"kind": {"path": {"parts": [{"kind": {"field": "b"}}]}}},
+            "limit": null}}"#;
+        let query = from_json(json).unwrap();
+        assert_eq!(query, parse_query("find {a: == 1} return .b").unwrap());
+        assert_eq!(query.to_string(), "find {a: == 1} return .b");
+
+        let json = r#"{"version": 1, "query": {"find": {"kind": "all"}, "ret": {"kind": {"path": {
+            "parts": [{"kind": {"field": "x"}}],
+            "default": {"object": [["k", {"number": {"uint": 1}}], ["j", "null"]]}}}},
+            "limit": null}}"#;
+        let query = from_json(json).unwrap();
+        assert_eq!(query.to_string(), "find {} return .x default={k: 1, j: null}");
+        assert_eq!(parse_query(&query.to_string()).unwrap(), query);
+    }
+
+    #[test]
+    fn errors() {
+        let error = from_json(r#"{"version": 2, "query": {}}"#).unwrap_err();
+        assert_eq!(error.to_string(), "unsupported version 2 of the query format, expected 1");
+        assert!(matches!(from_json(r#"{"version": 1, "query": {"find": {"kind": "some"}}}"#),
+                         Err(SchemaError::Json(_))));
+    }
+}
@@ -0,0 +1,181 @@
+//! Relevance scoring of `~=` and `~N=` conditions
+//!
+//! Only word matches contribute to the score of a document. The scores of all matching conditions
+//! are summed up, a boost multiplies the score of the conditions it applies to. How a single match
+//! is scored is up to the `Scorer`, the default is `Bm25`.
+
+use std::collections::HashMap;
+
+use serde_json::Value as Json;
+
+use text::{tokenize, Phrase};
+
+/// Calculates the score of a text that matched a phrase
+pub trait Scorer {
+    /// `words` is the tokenized text, `field` the statistics of the field the text is from
+    fn score(&self, phrase: &Phrase, words: &[String], field: &FieldStats) -> f64;
+}
+
+/// Statistics about the texts of a single field across all documents
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldStats {
+    /// Number of documents that contain the field
+    pub documents: usize,
+    /// Number of texts, it differs from the number of documents if the field is within an array
+    pub texts: usize,
+    /// Number of words of all texts
+    pub words: usize,
+    document_frequencies: HashMap<String, usize>,
+}
+
+impl FieldStats {
+    /// The number of documents where this field contains the word
+    pub fn document_frequency(&self, word: &str) -> usize {
+        self.document_frequencies.get(word).cloned().unwrap_or(0)
+    }
+
+    /// The average number of words of a text
+    pub fn average_length(&self) -> f64 {
+        if self.texts == 0 {
+            0.0
+        } else {
+            self.words as f64 / self.texts as f64
+        }
+    }
+}
+
+/// Statistics about a collection of documents, they are the base for scoring
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Statistics {
+    fields: HashMap<Vec<String>, FieldStats>,
+}
+
+impl Statistics {
+    pub fn new(documents: &[Json]) -> Statistics {
+        let mut statistics = Statistics::default();
+        for document in documents {
+            let mut texts = HashMap::new();
+            collect_texts(document, &mut Vec::new(), &mut texts);
+            for (path, texts) in texts {
+                let field = statistics.fields.entry(path).or_default();
+                field.documents += 1;
+                field.texts += texts.len();
+                let mut seen = Vec::new();
+                for word in texts.into_iter().flatten() {
+                    field.words += 1;
+                    if !seen.contains(&word) {
+                        *field.document_frequencies.entry(word.clone()).or_insert(0) += 1;
+                        seen.push(word);
+                    }
+                }
+            }
+        }
+        statistics
+    }
+
+    /// Returns the statistics of a field. The path consists of the keys only, array elements
+    /// share the path of the array.
+    pub fn field(&self, path: &[String]) -> Option<&FieldStats> {
+        self.fields.get(path)
+    }
+}
+
+/// Collects the tokenized strings of a document by their path
+fn collect_texts(value: &Json, path: &mut Vec<String>,
+                 texts: &mut HashMap<Vec<String>, Vec<Vec<String>>>) {
+    match *value {
+        Json::String(ref text) => texts.entry(path.clone()).or_default().push(tokenize(text)),
+        Json::Array(ref elements) => {
+            for element in elements {
+                collect_texts(element, path, texts);
+            }
+        },
+        Json::Object(ref members) => {
+            for (key, value) in members {
+                path.push(key.clone());
+                collect_texts(value, path, texts);
+                path.pop();
+            }
+        },
+        _ => {},
+    }
+}
+
+/// The Okapi BM25 ranking function
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bm25 {
+    /// Controls how quickly additional occurrences of a word stop increasing the score
+    pub k1: f64,
+    /// Controls how much longer texts are penalized
+    pub b: f64,
+}
+
+impl Default for Bm25 {
+    fn default() -> Bm25 {
+        Bm25 {
+            k1: 1.2,
+            b: 0.75,
+        }
+    }
+}
+
+impl Scorer for Bm25 {
+    fn score(&self, phrase: &Phrase, words: &[String], field: &FieldStats) -> f64 {
+        let documents = field.documents as f64;
+        let length_ratio = match field.average_length() {
+            average if average > 0.0 => words.len() as f64 / average,
+            _ => 1.0,
+        };
+        phrase.words()
+            .iter()
+            .map(|word| {
+                let frequency = words.iter().filter(|&candidate| candidate == word).count() as f64;
+                let document_frequency = field.document_frequency(word) as f64;
+                let idf = (1.0 + (documents - document_frequency + 0.5) /
+                           (document_frequency + 0.5)).ln();
+                idf * frequency * (self.k1 + 1.0) /
+                    (frequency + self.k1 * (1.0 - self.b + self.b * length_ratio))
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statistics() -> Statistics {
+        Statistics::new(&[json!({"title": "The quick brown fox", "tags": ["fox", "animal"]}),
+                          json!({"title": "The lazy dog"}),
+                          json!({"title": "A fox and another fox", "tags": []})])
+    }
+
+    #[test]
+    fn field_statistics() {
+        let statistics = statistics();
+        let title = statistics.field(&["title".to_string()]).unwrap();
+        assert_eq!((title.documents, title.texts, title.words), (3, 3, 12));
+        assert_eq!(title.document_frequency("fox"), 2);
+        assert_eq!(title.document_frequency("the"), 2);
+        assert_eq!(title.document_frequency("cat"), 0);
+        assert_eq!(title.average_length(), 4.0);
+        let tags = statistics.field(&["tags".to_string()]).unwrap();
+        assert_eq!((tags.documents, tags.texts, tags.words), (1, 2, 2));
+        assert_eq!(statistics.field(&["missing".to_string()]), None);
+    }
+
+    #[test]
+    fn bm25() {
+        let statistics = statistics();
+        let title = statistics.field(&["title".to_string()]).unwrap();
+        let score = |phrase: &str, text: &str| {
+            Bm25::default().score(&Phrase::exact(phrase), &tokenize(text), title)
+        };
+        // More occurrences score higher, rarer words score higher
+        assert!(score("fox", "A fox and another fox") > score("fox", "The quick brown fox"));
+        assert!(score("lazy", "The lazy dog") > score("the", "The lazy dog"));
+        assert!(score("quick brown", "The quick brown fox") >
+                score("quick", "The quick brown fox"));
+        assert_eq!(score("cat", "The lazy dog"), 0.0);
+    }
+}
@@ -0,0 +1,169 @@
+//! Splitting of files that contain several queries
+//!
+//! Queries are separated by `;`, by blank lines or by a line that starts with `find`, so files with
+//! one query per line work as well. Separators within strings and comments don't count, so a query
+//! may span several lines as long as none of them is empty or starts a new query.
+
+/// A single query of a file
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryText<'a> {
+    /// The query without the surrounding whitespace and separators
+    pub text: &'a str,
+    /// The byte offset of the query within the file
+    pub offset: usize,
+    /// The line (starting at 1) the query starts at
+    pub line: usize,
+    /// The column (starting at 1, counted in characters) the query starts at
+    pub column: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Code,
+    String,
+    LineComment,
+    BlockComment,
+}
+
+/// Returns the queries of a file in the order they appear. Parts that contain nothing but
+/// comments aren't queries and are skipped.
+pub fn split_queries(input: &str) -> Vec<QueryText<'_>> {
+    let mut queries = Vec::new();
+    let mut state = State::Code;
+    // The start of the current query and whether it contains more than comments
+    let mut start = 0;
+    let mut has_code = false;
+    // Whether only whitespace came since the last line break
+    let mut blank = false;
+    // Where the current line starts if it doesn't start within a comment or string, whether it
+    // contains code and where the lines with nothing but comments since the last code start
+    let mut line_start = Some(0);
+    let mut line_code = false;
+    let mut comments = None;
+
+    let mut chars = input.char_indices().peekable();
+    while let Some((pos, cc)) = chars.next() {
+        let separator = match (state, cc) {
+            (State::Code, ';') => true,
+            (State::Code, '\n') | (State::LineComment, '\n') if blank => true,
+            _ => false,
+        };
+        if separator {
+            push_query(input, start, pos, has_code, &mut queries);
+            start = pos + 1;
+            has_code = false;
+            comments = None;
+            state = State::Code;
+            continue;
+        }
+        // A `find` at the start of a line starts the next query, together with the comments
+        // right before it
+        if state == State::Code && blank && has_code && starts_query(&input[pos..]) {
+            let end = comments.unwrap_or(pos);
+            push_query(input, start, end, has_code, &mut queries);
+            start = end;
+            has_code = false;
+        }
+
+        let mut closes_comment = false;
+        match (state, cc) {
+            (State::Code, '"') => state = State::String,
+            (State::Code, '#') => state = State::LineComment,
+            (State::Code, '/') if chars.peek().is_some_and(|&(_, next)| next == '*') => {
+                chars.next();
+                state = State::BlockComment;
+            },
+            (State::String, '\\') => {
+                chars.next();
+            },
+            (State::String, '"') => state = State::Code,
+            (State::LineComment, '\n') => state = State::Code,
+            (State::BlockComment, '*') if chars.peek().is_some_and(|&(_, next)| next == '/') => {
+                chars.next();
+                state = State::Code;
+                closes_comment = true;
+            },
+            _ => {},
+        }
+        if state == State::Code && !closes_comment && !cc.is_whitespace() && cc != '/' {
+            has_code = true;
+            line_code = true;
+        }
+        if cc == '\n' {
+            comments = if line_code { None } else { comments.or(line_start) };
+            blank = state != State::BlockComment && state != State::String;
+            line_start = if blank { Some(pos + 1) } else { None };
+            line_code = false;
+        } else if !cc.is_whitespace() {
+            blank = false;
+        }
+    }
+    push_query(input, start, input.len(), has_code, &mut queries);
+    queries
+}
+
+/// Whether the text starts with the `find` keyword
+fn starts_query(rest: &str) -> bool {
+    rest.starts_with("find") &&
+        rest["find".len()..].chars().next().is_none_or(|cc| cc.is_whitespace() || cc == '{')
+}
+
+fn push_query<'a>(input: &'a str, start: usize, end: usize, has_code: bool,
+                  queries: &mut Vec<QueryText<'a>>) {
+    if !has_code {
+        return;
+    }
+    let part = &input[start..end];
+    let text = part.trim();
+    let offset = start + (part.len() - part.trim_start().len());
+    let before = &input[..offset];
+    let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+    queries.push(QueryText {
+        text,
+        offset,
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(input: &str) -> Vec<(&str, usize, usize)> {
+        split_queries(input)
+            .into_iter()
+            .map(|query| (query.text, query.line, query.column))
+            .collect()
+    }
+
+    #[test]
+    fn separators() {
+        assert_eq!(texts("find {a: == 1}\nfind {b: == 2}\n  find {}\nfind{c: == 3}\n"),
+                   vec![("find {a: == 1}", 1, 1), ("find {b: == 2}", 2, 1), ("find {}", 3, 3),
+                        ("find{c: == 3}", 4, 1)]);
+        assert_eq!(texts("find {a: == 1}\n  && {b: == 2}\nreturn .a\nfind {\nfind: == 3}"),
+                   vec![("find {a: == 1}\n  && {b: == 2}\nreturn .a", 1, 1),
+                        ("find {\nfind: == 3}", 4, 1)]);
+        assert_eq!(texts("find {a: == 1}; find {b: == 2};\n\n  find {\n  c: == 3\n}\n \t\nfind {}"),
+                   vec![("find {a: == 1}", 1, 1), ("find {b: == 2}", 1, 17),
+                        ("find {\n  c: == 3\n}", 3, 3), ("find {}", 7, 1)]);
+        assert_eq!(texts(";;\n\n"), vec![]);
+    }
+
+    #[test]
+    fn strings_and_comments() {
+        assert_eq!(texts("find {a: == \";\n\n\"}"), vec![("find {a: == \";\n\n\"}", 1, 1)]);
+        assert_eq!(texts("# only a comment; really\n\nfind {a: == 1} /* a;\n\nb */ return .a"),
+                   vec![("find {a: == 1} /* a;\n\nb */ return .a", 3, 1)]);
+        assert_eq!(texts("# leading comment\nfind {a: == 1} # trailing\n\nfind {}"),
+                   vec![("# leading comment\nfind {a: == 1} # trailing", 1, 1),
+                        ("find {}", 4, 1)]);
+        assert_eq!(texts("/* note */\n\nfind {}"), vec![("find {}", 3, 1)]);
+        assert_eq!(texts("/* a */ /* b */; find {}"), vec![("find {}", 1, 18)]);
+        assert_eq!(texts("find {a: == 1}\n# next one\nfind {} /*\nfind */"),
+                   vec![("find {a: == 1}", 1, 1), ("# next one\nfind {} /*\nfind */", 2, 1)]);
+        assert_eq!(texts("find {a: == 1} /* a\nb */\n/* c\nd */\nfind {}"),
+                   vec![("find {a: == 1} /* a\nb */", 1, 1), ("/* c\nd */\nfind {}", 3, 1)]);
+    }
+}
@@ -0,0 +1,127 @@
+//! Full-text matching as it is used by the `~=` and `~N=` operators
+//!
+//! Texts are split into words at Unicode word boundaries, all words are lowercased. `~=` matches
+//! if the words of the phrase appear in the text in exactly that order, `~N=` matches if they
+//! appear in that order with at most N other words between each of them.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Splits a text into lowercased words, punctuation and whitespace are dropped
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.unicode_words().map(|word| word.to_lowercase()).collect()
+}
+
+/// A phrase of a `~=` or `~N=` condition
+#[derive(Clone, Debug, PartialEq)]
+pub struct Phrase {
+    words: Vec<String>,
+    distance: u64,
+}
+
+impl Phrase {
+    /// Creates a phrase that matches exactly (`~=`)
+    pub fn exact(phrase: &str) -> Phrase {
+        Phrase {
+            words: tokenize(phrase),
+            distance: 0,
+        }
+    }
+
+    /// Creates a phrase whose words may have up to `distance` other words between them (`~N=`)
+    pub fn within(phrase: &str, distance: u64) -> Phrase {
+        Phrase {
+            words: tokenize(phrase),
+            distance,
+        }
+    }
+
+    /// Creates the phrase the way the operator specifies it, `None` is an exact match
+    pub fn new(phrase: &str, distance: Option<u64>) -> Phrase {
+        match distance {
+            Some(distance) => Phrase::within(phrase, distance),
+            None => Phrase::exact(phrase),
+        }
+    }
+
+    /// The lowercased words of the phrase
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    /// Returns whether the text contains the phrase. A phrase without any words never matches.
+    pub fn matches(&self, text: &str) -> bool {
+        self.matches_words(&tokenize(text))
+    }
+
+    /// Like `matches()`, but on a text that was already tokenized
+    pub fn matches_words(&self, words: &[String]) -> bool {
+        !self.positions(words).is_empty()
+    }
+
+    /// Returns the positions of the first word of all occurrences of the phrase
+    pub fn positions(&self, words: &[String]) -> Vec<usize> {
+        let (first, rest) = match self.words.split_first() {
+            Some(split) => split,
+            None => return Vec::new(),
+        };
+        let window = self.distance as usize + 1;
+        words.iter()
+            .enumerate()
+            .filter(|&(_, word)| word == first)
+            .filter(|&(start, _)| {
+                // The window of a word depends on where the previous one is, so all positions
+                // the previous word can be at are tracked
+                let mut reachable = vec![start];
+                rest.iter().all(|wanted| {
+                    let mut next: Vec<usize> = Vec::new();
+                    for &position in &reachable {
+                        let end = words.len().min(position.saturating_add(window + 1));
+                        let from = next.last().map_or(position + 1, |&last| last.max(position) + 1);
+                        next.extend((from..end).filter(|&candidate| words[candidate] == *wanted));
+                    }
+                    reachable = next;
+                    !reachable.is_empty()
+                })
+            })
+            .map(|(start, _)| start)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_words() {
+        assert_eq!(tokenize("Hello, World! It's  Zürich-Straße 42."),
+                   vec!["hello", "world", "it's", "zürich", "straße", "42"]);
+        assert_eq!(tokenize(" ... "), Vec::<String>::new());
+    }
+
+    #[test]
+    fn exact_phrase() {
+        let phrase = Phrase::exact("Quick brown");
+        assert!(phrase.matches("The quick brown fox"));
+        assert!(phrase.matches("THE QUICK, BROWN FOX"));
+        assert!(!phrase.matches("The brown quick fox"));
+        assert!(!phrase.matches("The quick red brown fox"));
+        assert!(!phrase.matches("The quick"));
+        assert!(!Phrase::exact("").matches("The quick brown fox"));
+        assert_eq!(phrase.positions(&tokenize("quick brown quick quick brown")), vec![0, 3]);
+    }
+
+    #[test]
+    fn proximity() {
+        let phrase = Phrase::within("quick fox", 2);
+        assert!(phrase.matches("the quick fox"));
+        assert!(phrase.matches("the quick brown fox"));
+        assert!(phrase.matches("the quick old brown fox"));
+        assert!(!phrase.matches("the quick and old brown fox"));
+        assert!(!phrase.matches("the fox is quick"));
+        assert!(Phrase::within("a b c", 1).matches("a x b x c"));
+        assert!(!Phrase::within("a b c", 1).matches("a x b x x c"));
+        assert!(Phrase::within("a b c", 1).matches("a b b x c"));
+        assert_eq!(Phrase::new("quick fox", Some(0)), Phrase::exact("quick fox"));
+    }
+}
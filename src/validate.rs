@@ -0,0 +1,356 @@
+//! Checks for queries that parse fine, but don't make sense
+//!
+//! Errors are queries that can't be run, warnings are queries that can be run, but most likely
+//! don't do what was intended.
+
+use std::fmt;
+
+use ast::{Aggregate, Ident, OrderBy, Predicate, PredicateKind, Projection, ProjectionKind, Query,
+          Span};
+use range::ranges;
+use visit::{walk_predicate, walk_projection, Visitor};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Stable identifiers for the kinds of problems the validator reports
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagnosticCode {
+    /// The return clause uses a variable that isn't bound in the find clause
+    UnknownVariable,
+    /// Aggregate functions are returned together with values of single documents
+    MixedAggregates,
+    /// A variable is bound in the find clause but never returned
+    UnusedVariable,
+    /// A variable is bound within a negation, so it never contains anything
+    NegatedVariable,
+    /// The order clause doesn't change the result of the aggregate functions
+    OrderWithAggregates,
+    /// `score()` is used, but there are no conditions that score
+    ScoreWithoutWordMatch,
+    /// Comparisons on the same field contradict each other, e.g. `a: > 10, a: < 5`
+    EmptyRange,
+}
+
+impl DiagnosticCode {
+    /// The code as it is shown to users and written to logs, e.g. `E0101`
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            DiagnosticCode::UnknownVariable => "E0101",
+            DiagnosticCode::MixedAggregates => "E0102",
+            DiagnosticCode::UnusedVariable => "W0101",
+            DiagnosticCode::NegatedVariable => "W0102",
+            DiagnosticCode::OrderWithAggregates => "W0103",
+            DiagnosticCode::ScoreWithoutWordMatch => "W0104",
+            DiagnosticCode::EmptyRange => "W0105",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match *self {
+            DiagnosticCode::UnknownVariable | DiagnosticCode::MixedAggregates => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A problem found by `validate()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub code: DiagnosticCode,
+    pub severity: Severity,
+    pub message: String,
+    /// The byte range of the part of the query the diagnostic is about, `None` if it isn't known
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn new(code: DiagnosticCode, message: String, span: Option<Span>) -> Diagnostic {
+        Diagnostic {
+            code,
+            severity: code.severity(),
+            message,
+            span,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}[{}]: {}", severity, self.code, self.message)
+    }
+}
+
+/// Returns all problems of the query, errors first
+pub fn validate(query: &Query) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    let mut variables = Variables::default();
+    variables.visit_predicate(&query.find);
+    let variables = variables.found;
+    let mut returned = Returned::default();
+    if let Some(ref ret) = query.ret {
+        returned.visit_projection(ret);
+    }
+    let returned = returned.0;
+
+    for name in &returned {
+        if !variables.iter().any(|&(declared, _)| declared == *name) {
+            diagnostics.push(Diagnostic::new(
+                DiagnosticCode::UnknownVariable,
+                format!("variable `{}` is not bound in the find clause", name),
+                Some(name.span)));
+        }
+    }
+    for &(name, negated) in &variables {
+        if negated {
+            diagnostics.push(Diagnostic::new(
+                DiagnosticCode::NegatedVariable,
+                format!("variable `{}` is bound within a negation and never has a value", name),
+                Some(name.span)));
+        } else if !returned.contains(&name) {
+            diagnostics.push(Diagnostic::new(
+                DiagnosticCode::UnusedVariable,
+                format!("variable `{}` is never returned", name),
+                Some(name.span)));
+        }
+    }
+
+    if let Some(ref ret) = query.ret {
+        let mut aggregates = Vec::new();
+        let mut single = Vec::new();
+        collect_projections(ret, &mut aggregates, &mut single);
+        if !aggregates.is_empty() {
+            for projection in single {
+                diagnostics.push(Diagnostic::new(
+                    DiagnosticCode::MixedAggregates,
+                    format!("`{}` can't be returned together with aggregate functions",
+                            projection),
+                    Some(projection.span)));
+            }
+            let order_matters = aggregates.iter().any(|aggregate| {
+                matches!(**aggregate,
+                         Aggregate::Array(_) | Aggregate::ArrayFlat(_) | Aggregate::Concat(..))
+            });
+            if let (Some(first), Some(last)) = (query.order.first(), query.order.last()) {
+                if !order_matters {
+                    diagnostics.push(Diagnostic::new(
+                        DiagnosticCode::OrderWithAggregates,
+                        "the order clause has no effect on the aggregate functions".to_string(),
+                        Some(Span::new(first.span.start, last.span.end))));
+                }
+            }
+        }
+    }
+
+    let score = query.order.iter()
+        .find(|order| order.by == Some(OrderBy::Score))
+        .map(|order| order.span)
+        .or_else(|| query.ret.as_ref().and_then(find_score));
+    if let Some(span) = score {
+        if !has_word_match(&query.find) {
+            diagnostics.push(Diagnostic::new(
+                DiagnosticCode::ScoreWithoutWordMatch,
+                "`score()` is always 0 without any `~=` or `~N=` conditions".to_string(),
+                Some(span)));
+        }
+    }
+
+    for range in ranges(&query.find) {
+        if let Some(conflict) = range.conflict {
+            diagnostics.push(Diagnostic::new(
+                DiagnosticCode::EmptyRange,
+                format!("the conditions on `{}` contradict each other and can never match \
+                         together", range.path),
+                Some(conflict)));
+        }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| !diagnostic.is_error());
+    diagnostics
+}
+
+/// Collects the names of the bound variables and whether they are within a negation
+#[derive(Default)]
+struct Variables<'a> {
+    found: Vec<(&'a Ident, bool)>,
+    negated: bool,
+}
+
+impl<'a> Visitor<'a> for Variables<'a> {
+    fn visit_predicate(&mut self, predicate: &'a Predicate) {
+        match predicate.kind {
+            PredicateKind::Bind(ref name, _) => {
+                let negated = self.negated;
+                match self.found.iter_mut().find(|&&mut (existing, _)| *existing == *name) {
+                    // A variable counts as negated only if it's negated everywhere
+                    Some(existing) => existing.1 = existing.1 && negated,
+                    None => self.found.push((name, negated)),
+                }
+                walk_predicate(self, predicate);
+            },
+            PredicateKind::Not(ref inner) => {
+                let negated = self.negated;
+                self.negated = true;
+                self.visit_predicate(inner);
+                self.negated = negated;
+            },
+            _ => walk_predicate(self, predicate),
+        }
+    }
+}
+
+/// Collects the variables the projection returns
+#[derive(Default)]
+struct Returned<'a>(Vec<&'a Ident>);
+
+impl<'a> Visitor<'a> for Returned<'a> {
+    fn visit_projection(&mut self, projection: &'a Projection) {
+        match projection.kind {
+            ProjectionKind::Bind(ref name, _) if !self.0.contains(&name) => self.0.push(name),
+            _ => walk_projection(self, projection),
+        }
+    }
+}
+
+/// Collects the aggregate functions and the projections that return values of single documents
+fn collect_projections<'a>(projection: &'a Projection, aggregates: &mut Vec<&'a Aggregate>,
+                           single: &mut Vec<&'a Projection>) {
+    match projection.kind {
+        ProjectionKind::Aggregate(ref aggregate) => aggregates.push(aggregate),
+        ProjectionKind::Object(ref members) => {
+            for (_, value) in members {
+                collect_projections(value, aggregates, single);
+            }
+        },
+        ProjectionKind::Array(ref values) => {
+            for value in values {
+                collect_projections(value, aggregates, single);
+            }
+        },
+        ProjectionKind::Value(_) => {},
+        ProjectionKind::All | ProjectionKind::Path(_) | ProjectionKind::Bind(..) |
+        ProjectionKind::Score => {
+            single.push(projection)
+        },
+    }
+}
+
+/// Returns the span of the first `score()` of the projection
+fn find_score(projection: &Projection) -> Option<Span> {
+    match projection.kind {
+        ProjectionKind::Score => Some(projection.span),
+        ProjectionKind::Object(ref members) => {
+            members.iter().filter_map(|(_, value)| find_score(value)).next()
+        },
+        ProjectionKind::Array(ref values) => values.iter().filter_map(find_score).next(),
+        _ => None,
+    }
+}
+
+fn has_word_match(predicate: &Predicate) -> bool {
+    match predicate.kind {
+        PredicateKind::WordMatch(..) => true,
+        // Negated conditions never score
+        PredicateKind::Not(_) => false,
+        PredicateKind::Object(_, ref inner) |
+        PredicateKind::Array(ref inner) |
+        PredicateKind::Bind(_, ref inner) |
+        PredicateKind::Boost(_, ref inner) => has_word_match(inner),
+        PredicateKind::And(ref left, ref right) | PredicateKind::Or(ref left, ref right) => {
+            has_word_match(left) || has_word_match(right)
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use parse_query;
+
+    fn check(query: &str) -> Vec<String> {
+        validate(&parse_query(query).unwrap())
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn valid() {
+        assert_eq!(check(r#"find {a: x::[== 1]} return {x: x, all: .}"#), Vec::<String>::new());
+        assert_eq!(check(r#"find {a: ~= "b"} order score() desc return [score(), .a]"#),
+                   Vec::<String>::new());
+        assert_eq!(check(r#"find {} order .a return {a: group(.b), c: array(.c), d: "text"}"#),
+                   Vec::<String>::new());
+    }
+
+    #[test]
+    fn variables() {
+        let query = r#"find {a: x::[== 1], c: z::[== 3]} && !{b: y::[== 2]}
+                       return [x, y.c, w]"#;
+        assert_eq!(check(query),
+                   vec!["error[E0101]: variable `w` is not bound in the find clause",
+                        "warning[W0101]: variable `z` is never returned",
+                        "warning[W0102]: variable `y` is bound within a negation and never has \
+                         a value"]);
+    }
+
+    #[test]
+    fn aggregates() {
+        assert_eq!(check(r#"find {} order .a return {a: group(.a), b: .b, c: count()}"#),
+                   vec!["error[E0102]: `.b` can't be returned together with aggregate functions",
+                        "warning[W0103]: the order clause has no effect on the aggregate \
+                         functions"]);
+        assert_eq!(check(r#"find {} order score() return [sum(.a), score()]"#),
+                   vec!["error[E0102]: `score()` can't be returned together with aggregate \
+                         functions",
+                        "warning[W0103]: the order clause has no effect on the aggregate \
+                         functions",
+                        "warning[W0104]: `score()` is always 0 without any `~=` or `~N=` \
+                         conditions"]);
+    }
+
+    #[test]
+    fn spans() {
+        let query = r#"find {a: x::[== 1]} order score() return {y: y, x: x}"#;
+        let spans: Vec<&str> = validate(&parse_query(query).unwrap())
+            .iter()
+            .map(|diagnostic| diagnostic.span.unwrap())
+            .map(|span| &query[span.start..span.end])
+            .collect();
+        assert_eq!(spans, vec!["y", "score()"]);
+    }
+
+    #[test]
+    fn contradictions() {
+        assert_eq!(check(r#"find {age: > 18, age: < 65, age: >= 20}"#), Vec::<String>::new());
+        assert_eq!(check(r#"find {age: > 10, age: < 5} || {a: {x: == 1, x: == 2}}"#),
+                   vec!["warning[W0105]: the conditions on `.age` contradict each other and can \
+                         never match together",
+                        "warning[W0105]: the conditions on `.a.x` contradict each other and can \
+                         never match together"]);
+        let query = r#"find {tags: [== "a", > "b"]}"#;
+        let diagnostics = validate(&parse_query(query).unwrap());
+        let span = diagnostics[0].span.unwrap();
+        assert_eq!(&query[span.start..span.end], r#"> "b""#);
+    }
+}
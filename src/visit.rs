@@ -0,0 +1,512 @@
+//! Traversals of the syntax tree
+//!
+//! `Visitor` walks a query by reference, `VisitorMut` by mutable reference and `Fold` takes it
+//! apart and builds a new one. Every node kind has a method that by default walks its children
+//! with the matching free function, e.g. `walk_predicate()`. An implementation only overrides the
+//! methods of the nodes it cares about and calls the walk function if it wants to go deeper.
+
+use ast::{Aggregate, Bbox, Ident, OrderBy, OrderKey, Path, PathPart, Predicate, PredicateKind,
+          Projection, ProjectionKind, Query, Value};
+
+/// Walks the syntax tree by reference
+pub trait Visitor<'ast> {
+    fn visit_query(&mut self, query: &'ast Query) {
+        walk_query(self, query)
+    }
+
+    fn visit_predicate(&mut self, predicate: &'ast Predicate) {
+        walk_predicate(self, predicate)
+    }
+
+    fn visit_ident(&mut self, _ident: &'ast Ident) {}
+
+    fn visit_value(&mut self, _value: &'ast Value) {}
+
+    fn visit_bbox(&mut self, _bbox: &'ast Bbox) {}
+
+    fn visit_order_key(&mut self, order_key: &'ast OrderKey) {
+        walk_order_key(self, order_key)
+    }
+
+    fn visit_projection(&mut self, projection: &'ast Projection) {
+        walk_projection(self, projection)
+    }
+
+    fn visit_aggregate(&mut self, aggregate: &'ast Aggregate) {
+        walk_aggregate(self, aggregate)
+    }
+
+    fn visit_path(&mut self, path: &'ast Path) {
+        walk_path(self, path)
+    }
+
+    fn visit_path_part(&mut self, _part: &'ast PathPart) {}
+}
+
+pub fn walk_query<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, query: &'ast Query) {
+    visitor.visit_predicate(&query.find);
+    for order_key in &query.order {
+        visitor.visit_order_key(order_key);
+    }
+    if let Some(ref ret) = query.ret {
+        visitor.visit_projection(ret);
+    }
+}
+
+pub fn walk_predicate<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V,
+                                                       predicate: &'ast Predicate) {
+    match predicate.kind {
+        PredicateKind::All => {},
+        PredicateKind::Object(ref key, ref inner) | PredicateKind::Bind(ref key, ref inner) => {
+            visitor.visit_ident(key);
+            visitor.visit_predicate(inner);
+        },
+        PredicateKind::Array(ref inner) | PredicateKind::Not(ref inner) |
+        PredicateKind::Boost(_, ref inner) => visitor.visit_predicate(inner),
+        PredicateKind::Equal(ref key, ref value) |
+        PredicateKind::Greater(ref key, ref value) |
+        PredicateKind::GreaterEqual(ref key, ref value) |
+        PredicateKind::Less(ref key, ref value) |
+        PredicateKind::LessEqual(ref key, ref value) |
+        PredicateKind::WordMatch(ref key, _, ref value) => {
+            if let Some(ref key) = *key {
+                visitor.visit_ident(key);
+            }
+            visitor.visit_value(value);
+        },
+        PredicateKind::Intersect(ref key, ref bbox) => {
+            if let Some(ref key) = *key {
+                visitor.visit_ident(key);
+            }
+            visitor.visit_bbox(bbox);
+        },
+        PredicateKind::And(ref left, ref right) | PredicateKind::Or(ref left, ref right) => {
+            visitor.visit_predicate(left);
+            visitor.visit_predicate(right);
+        },
+    }
+}
+
+pub fn walk_order_key<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V,
+                                                       order_key: &'ast OrderKey) {
+    if let Some(OrderBy::Path(ref path)) = order_key.by {
+        visitor.visit_path(path);
+    }
+}
+
+pub fn walk_projection<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V,
+                                                        projection: &'ast Projection) {
+    match projection.kind {
+        ProjectionKind::All | ProjectionKind::Value(_) | ProjectionKind::Score => {},
+        ProjectionKind::Path(ref path) => visitor.visit_path(path),
+        ProjectionKind::Bind(ref name, ref path) => {
+            visitor.visit_ident(name);
+            if let Some(ref path) = *path {
+                visitor.visit_path(path);
+            }
+        },
+        ProjectionKind::Object(ref members) => {
+            for (key, value) in members {
+                visitor.visit_ident(key);
+                visitor.visit_projection(value);
+            }
+        },
+        ProjectionKind::Array(ref values) => {
+            for value in values {
+                visitor.visit_projection(value);
+            }
+        },
+        ProjectionKind::Aggregate(ref aggregate) => visitor.visit_aggregate(aggregate),
+    }
+}
+
+pub fn walk_aggregate<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V,
+                                                       aggregate: &'ast Aggregate) {
+    match *aggregate {
+        Aggregate::Count => {},
+        Aggregate::Array(ref path) | Aggregate::ArrayFlat(ref path) | Aggregate::Avg(ref path) |
+        Aggregate::Concat(ref path, _) | Aggregate::Group(ref path, _) |
+        Aggregate::Max(ref path) | Aggregate::MaxArray(ref path) | Aggregate::Min(ref path) |
+        Aggregate::MinArray(ref path) | Aggregate::Sum(ref path) => visitor.visit_path(path),
+    }
+}
+
+pub fn walk_path<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, path: &'ast Path) {
+    for part in &path.parts {
+        visitor.visit_path_part(part);
+    }
+}
+
+/// Walks the syntax tree by mutable reference, e.g. to change nodes in place
+pub trait VisitorMut {
+    fn visit_query_mut(&mut self, query: &mut Query) {
+        walk_query_mut(self, query)
+    }
+
+    fn visit_predicate_mut(&mut self, predicate: &mut Predicate) {
+        walk_predicate_mut(self, predicate)
+    }
+
+    fn visit_ident_mut(&mut self, _ident: &mut Ident) {}
+
+    fn visit_value_mut(&mut self, _value: &mut Value) {}
+
+    fn visit_bbox_mut(&mut self, _bbox: &mut Bbox) {}
+
+    fn visit_order_key_mut(&mut self, order_key: &mut OrderKey) {
+        walk_order_key_mut(self, order_key)
+    }
+
+    fn visit_projection_mut(&mut self, projection: &mut Projection) {
+        walk_projection_mut(self, projection)
+    }
+
+    fn visit_aggregate_mut(&mut self, aggregate: &mut Aggregate) {
+        walk_aggregate_mut(self, aggregate)
+    }
+
+    fn visit_path_mut(&mut self, path: &mut Path) {
+        walk_path_mut(self, path)
+    }
+
+    fn visit_path_part_mut(&mut self, _part: &mut PathPart) {}
+}
+
+pub fn walk_query_mut<V: VisitorMut + ?Sized>(visitor: &mut V, query: &mut Query) {
+    visitor.visit_predicate_mut(&mut query.find);
+    for order_key in &mut query.order {
+        visitor.visit_order_key_mut(order_key);
+    }
+    if let Some(ref mut ret) = query.ret {
+        visitor.visit_projection_mut(ret);
+    }
+}
+
+pub fn walk_predicate_mut<V: VisitorMut + ?Sized>(visitor: &mut V, predicate: &mut Predicate) {
+    match predicate.kind {
+        PredicateKind::All => {},
+        PredicateKind::Object(ref mut key, ref mut inner) |
+        PredicateKind::Bind(ref mut key, ref mut inner) => {
+            visitor.visit_ident_mut(key);
+            visitor.visit_predicate_mut(inner);
+        },
+        PredicateKind::Array(ref mut inner) | PredicateKind::Not(ref mut inner) |
+        PredicateKind::Boost(_, ref mut inner) => visitor.visit_predicate_mut(inner),
+        PredicateKind::Equal(ref mut key, ref mut value) |
+        PredicateKind::Greater(ref mut key, ref mut value) |
+        PredicateKind::GreaterEqual(ref mut key, ref mut value) |
+        PredicateKind::Less(ref mut key, ref mut value) |
+        PredicateKind::LessEqual(ref mut key, ref mut value) |
+        PredicateKind::WordMatch(ref mut key, _, ref mut value) => {
+            if let Some(ref mut key) = *key {
+                visitor.visit_ident_mut(key);
+            }
+            visitor.visit_value_mut(value);
+        },
+        PredicateKind::Intersect(ref mut key, ref mut bbox) => {
+            if let Some(ref mut key) = *key {
+                visitor.visit_ident_mut(key);
+            }
+            visitor.visit_bbox_mut(bbox);
+        },
+        PredicateKind::And(ref mut left, ref mut right) |
+        PredicateKind::Or(ref mut left, ref mut right) => {
+            visitor.visit_predicate_mut(left);
+            visitor.visit_predicate_mut(right);
+        },
+    }
+}
+
+pub fn walk_order_key_mut<V: VisitorMut + ?Sized>(visitor: &mut V, order_key: &mut OrderKey) {
+    if let Some(OrderBy::Path(ref mut path)) = order_key.by {
+        visitor.visit_path_mut(path);
+    }
+}
+
+pub fn walk_projection_mut<V: VisitorMut + ?Sized>(visitor: &mut V,
+                                                   projection: &mut Projection) {
+    match projection.kind {
+        ProjectionKind::All | ProjectionKind::Value(_) | ProjectionKind::Score => {},
+        ProjectionKind::Path(ref mut path) => visitor.visit_path_mut(path),
+        ProjectionKind::Bind(ref mut name, ref mut path) => {
+            visitor.visit_ident_mut(name);
+            if let Some(ref mut path) = *path {
+                visitor.visit_path_mut(path);
+            }
+        },
+        ProjectionKind::Object(ref mut members) => {
+            for (key, value) in members {
+                visitor.visit_ident_mut(key);
+                visitor.visit_projection_mut(value);
+            }
+        },
+        ProjectionKind::Array(ref mut values) => {
+            for value in values {
+                visitor.visit_projection_mut(value);
+            }
+        },
+        ProjectionKind::Aggregate(ref mut aggregate) => visitor.visit_aggregate_mut(aggregate),
+    }
+}
+
+pub fn walk_aggregate_mut<V: VisitorMut + ?Sized>(visitor: &mut V, aggregate: &mut Aggregate) {
+    match *aggregate {
+        Aggregate::Count => {},
+        Aggregate::Array(ref mut path) | Aggregate::ArrayFlat(ref mut path) |
+        Aggregate::Avg(ref mut path) | Aggregate::Concat(ref mut path, _) |
+        Aggregate::Group(ref mut path, _) | Aggregate::Max(ref mut path) |
+        Aggregate::MaxArray(ref mut path) | Aggregate::Min(ref mut path) |
+        Aggregate::MinArray(ref mut path) | Aggregate::Sum(ref mut path) => {
+            visitor.visit_path_mut(path)
+        },
+    }
+}
+
+pub fn walk_path_mut<V: VisitorMut + ?Sized>(visitor: &mut V, path: &mut Path) {
+    for part in &mut path.parts {
+        visitor.visit_path_part_mut(part);
+    }
+}
+
+/// Takes the syntax tree apart and builds a new one, e.g. to replace nodes by nodes of another
+/// kind
+pub trait Fold {
+    fn fold_query(&mut self, query: Query) -> Query {
+        fold_query(self, query)
+    }
+
+    fn fold_predicate(&mut self, predicate: Predicate) -> Predicate {
+        fold_predicate(self, predicate)
+    }
+
+    fn fold_ident(&mut self, ident: Ident) -> Ident {
+        ident
+    }
+
+    fn fold_value(&mut self, value: Value) -> Value {
+        value
+    }
+
+    fn fold_bbox(&mut self, bbox: Bbox) -> Bbox {
+        bbox
+    }
+
+    fn fold_order_key(&mut self, order_key: OrderKey) -> OrderKey {
+        fold_order_key(self, order_key)
+    }
+
+    fn fold_projection(&mut self, projection: Projection) -> Projection {
+        fold_projection(self, projection)
+    }
+
+    fn fold_aggregate(&mut self, aggregate: Aggregate) -> Aggregate {
+        fold_aggregate(self, aggregate)
+    }
+
+    fn fold_path(&mut self, path: Path) -> Path {
+        fold_path(self, path)
+    }
+
+    fn fold_path_part(&mut self, part: PathPart) -> PathPart {
+        part
+    }
+}
+
+pub fn fold_query<F: Fold + ?Sized>(folder: &mut F, query: Query) -> Query {
+    Query {
+        find: folder.fold_predicate(query.find),
+        order: query.order.into_iter().map(|key| folder.fold_order_key(key)).collect(),
+        ret: query.ret.map(|ret| folder.fold_projection(ret)),
+        ..query
+    }
+}
+
+pub fn fold_predicate<F: Fold + ?Sized>(folder: &mut F, predicate: Predicate) -> Predicate {
+    let Predicate { kind, span } = predicate;
+    let kind = match kind {
+        PredicateKind::All => PredicateKind::All,
+        PredicateKind::Object(key, value) => {
+            PredicateKind::Object(folder.fold_ident(key), inner(folder, *value))
+        },
+        PredicateKind::Array(elements) => PredicateKind::Array(inner(folder, *elements)),
+        PredicateKind::Bind(name, value) => {
+            PredicateKind::Bind(folder.fold_ident(name), inner(folder, *value))
+        },
+        PredicateKind::Not(negated) => PredicateKind::Not(inner(folder, *negated)),
+        PredicateKind::Boost(boost, boosted) => {
+            PredicateKind::Boost(boost, inner(folder, *boosted))
+        },
+        PredicateKind::Equal(key, value) => {
+            PredicateKind::Equal(fold_key(folder, key), folder.fold_value(value))
+        },
+        PredicateKind::Greater(key, value) => {
+            PredicateKind::Greater(fold_key(folder, key), folder.fold_value(value))
+        },
+        PredicateKind::GreaterEqual(key, value) => {
+            PredicateKind::GreaterEqual(fold_key(folder, key), folder.fold_value(value))
+        },
+        PredicateKind::Intersect(key, bbox) => {
+            PredicateKind::Intersect(fold_key(folder, key), folder.fold_bbox(bbox))
+        },
+        PredicateKind::Less(key, value) => {
+            PredicateKind::Less(fold_key(folder, key), folder.fold_value(value))
+        },
+        PredicateKind::LessEqual(key, value) => {
+            PredicateKind::LessEqual(fold_key(folder, key), folder.fold_value(value))
+        },
+        PredicateKind::WordMatch(key, distance, value) => {
+            PredicateKind::WordMatch(fold_key(folder, key), distance, folder.fold_value(value))
+        },
+        PredicateKind::And(left, right) => {
+            PredicateKind::And(inner(folder, *left), inner(folder, *right))
+        },
+        PredicateKind::Or(left, right) => {
+            PredicateKind::Or(inner(folder, *left), inner(folder, *right))
+        },
+    };
+    Predicate { kind, span }
+}
+
+fn inner<F: Fold + ?Sized>(folder: &mut F, inner: Predicate) -> Box<Predicate> {
+    Box::new(folder.fold_predicate(inner))
+}
+
+fn fold_key<F: Fold + ?Sized>(folder: &mut F, key: Option<Ident>) -> Option<Ident> {
+    key.map(|key| folder.fold_ident(key))
+}
+
+pub fn fold_order_key<F: Fold + ?Sized>(folder: &mut F, order_key: OrderKey) -> OrderKey {
+    OrderKey {
+        by: order_key.by.map(|by| match by {
+            OrderBy::Path(path) => OrderBy::Path(folder.fold_path(path)),
+            OrderBy::Score => OrderBy::Score,
+        }),
+        ..order_key
+    }
+}
+
+pub fn fold_projection<F: Fold + ?Sized>(folder: &mut F, projection: Projection) -> Projection {
+    let Projection { kind, span } = projection;
+    let kind = match kind {
+        ProjectionKind::All => ProjectionKind::All,
+        ProjectionKind::Value(value) => ProjectionKind::Value(value),
+        ProjectionKind::Path(path) => ProjectionKind::Path(folder.fold_path(path)),
+        ProjectionKind::Bind(name, path) => {
+            ProjectionKind::Bind(folder.fold_ident(name), path.map(|path| folder.fold_path(path)))
+        },
+        ProjectionKind::Object(members) => ProjectionKind::Object(
+            members.into_iter()
+                .map(|(key, value)| (folder.fold_ident(key), folder.fold_projection(value)))
+                .collect()),
+        ProjectionKind::Array(values) => ProjectionKind::Array(
+            values.into_iter().map(|value| folder.fold_projection(value)).collect()),
+        ProjectionKind::Aggregate(aggregate) => {
+            ProjectionKind::Aggregate(folder.fold_aggregate(aggregate))
+        },
+        ProjectionKind::Score => ProjectionKind::Score,
+    };
+    Projection { kind, span }
+}
+
+pub fn fold_aggregate<F: Fold + ?Sized>(folder: &mut F, aggregate: Aggregate) -> Aggregate {
+    match aggregate {
+        Aggregate::Array(path) => Aggregate::Array(folder.fold_path(path)),
+        Aggregate::ArrayFlat(path) => Aggregate::ArrayFlat(folder.fold_path(path)),
+        Aggregate::Avg(path) => Aggregate::Avg(folder.fold_path(path)),
+        Aggregate::Count => Aggregate::Count,
+        Aggregate::Concat(path, separator) => Aggregate::Concat(folder.fold_path(path), separator),
+        Aggregate::Group(path, order) => Aggregate::Group(folder.fold_path(path), order),
+        Aggregate::Max(path) => Aggregate::Max(folder.fold_path(path)),
+        Aggregate::MaxArray(path) => Aggregate::MaxArray(folder.fold_path(path)),
+        Aggregate::Min(path) => Aggregate::Min(folder.fold_path(path)),
+        Aggregate::MinArray(path) => Aggregate::MinArray(folder.fold_path(path)),
+        Aggregate::Sum(path) => Aggregate::Sum(folder.fold_path(path)),
+    }
+}
+
+pub fn fold_path<F: Fold + ?Sized>(folder: &mut F, path: Path) -> Path {
+    Path {
+        parts: path.parts.into_iter().map(|part| folder.fold_path_part(part)).collect(),
+        ..path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ast::PathPartKind;
+    use parse_query;
+
+    const QUERY: &str = "find {a: {b: == 1}, c: [~= \"x\"]} || {d: x::[> 2]} \
+                         order .a.b desc return {e: .c[0], f: sum(.d), g: x}";
+
+    #[derive(Default)]
+    struct Names<'ast> {
+        idents: Vec<&'ast str>,
+        fields: Vec<&'ast str>,
+    }
+
+    impl<'ast> Visitor<'ast> for Names<'ast> {
+        fn visit_ident(&mut self, ident: &'ast Ident) {
+            self.idents.push(ident.as_str());
+        }
+
+        fn visit_path_part(&mut self, part: &'ast PathPart) {
+            if let PathPartKind::Field(ref field) = part.kind {
+                self.fields.push(field);
+            }
+        }
+    }
+
+    #[test]
+    fn visitor() {
+        let query = parse_query(QUERY).unwrap();
+        let mut names = Names::default();
+        names.visit_query(&query);
+        assert_eq!(names.idents, vec!["a", "b", "c", "d", "x", "e", "f", "g", "x"]);
+        assert_eq!(names.fields, vec!["a", "b", "c", "d"]);
+    }
+
+    struct Upper;
+
+    impl VisitorMut for Upper {
+        fn visit_ident_mut(&mut self, ident: &mut Ident) {
+            ident.name = ident.name.to_uppercase();
+        }
+
+        fn visit_path_part_mut(&mut self, part: &mut PathPart) {
+            if let PathPartKind::Field(ref mut field) = part.kind {
+                *field = field.to_uppercase();
+            }
+        }
+    }
+
+    #[test]
+    fn visitor_mut() {
+        let mut query = parse_query(QUERY).unwrap();
+        Upper.visit_query_mut(&mut query);
+        assert_eq!(query.to_string(),
+                   "find {A: {B: == 1}, C: [~= \"x\"] || D: X::[> 2]} order .A.B desc \
+                    return {E: .C[0], F: sum(.D), G: X}");
+    }
+
+    /// Removes all boosts
+    struct Unboost;
+
+    impl Fold for Unboost {
+        fn fold_predicate(&mut self, predicate: Predicate) -> Predicate {
+            match predicate.kind {
+                PredicateKind::Boost(_, inner) => self.fold_predicate(*inner),
+                kind => fold_predicate(self, Predicate { kind, ..predicate }),
+            }
+        }
+    }
+
+    #[test]
+    fn fold() {
+        let query = parse_query("find {a: {b: ~= \"x\"}^2, c: [== 1]^3} return .a").unwrap();
+        assert_eq!(Unboost.fold_query(query).to_string(),
+                   "find {a: {b: ~= \"x\"}, c: [== 1]} return .a");
+    }
+}